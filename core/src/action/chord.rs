@@ -0,0 +1,284 @@
+use super::{key::create_command, KeyCode};
+use crate::error::{Error, Result};
+use std::{fmt, str::FromStr};
+
+// Modifiers held around at most one other key, always pressed in the order
+// Ctrl, Alt, Shift, Super so equal chords encode to the same bytes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    modifiers: Vec<KeyCode>,
+    key: Option<KeyCode>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChordBuilder {
+    modifiers: Vec<KeyCode>,
+    invalid: Option<KeyCode>,
+}
+
+impl Chord {
+    pub fn new(key: KeyCode) -> Result<Self> {
+        ChordBuilder::default().key(key)
+    }
+
+    pub fn builder() -> ChordBuilder {
+        ChordBuilder::default()
+    }
+
+    pub fn ctrl() -> ChordBuilder {
+        ChordBuilder::default().ctrl()
+    }
+
+    pub fn alt() -> ChordBuilder {
+        ChordBuilder::default().alt()
+    }
+
+    pub fn shift() -> ChordBuilder {
+        ChordBuilder::default().shift()
+    }
+
+    pub fn super_() -> ChordBuilder {
+        ChordBuilder::default().super_()
+    }
+
+    pub fn modifiers(&self) -> &[KeyCode] {
+        &self.modifiers
+    }
+
+    pub fn key(&self) -> Option<KeyCode> {
+        self.key
+    }
+
+    // Every key in the order it's pressed
+    pub fn keys(&self) -> Vec<KeyCode> {
+        self.modifiers.iter().copied().chain(self.key).collect()
+    }
+
+    // Same bytes as `create_command` with `keys`
+    pub fn packet(&self) -> Vec<u8> {
+        create_command(self.keys())
+    }
+}
+
+impl ChordBuilder {
+    pub fn ctrl(self) -> Self {
+        self.modifier(KeyCode::Control)
+    }
+
+    pub fn alt(self) -> Self {
+        self.modifier(KeyCode::Alt)
+    }
+
+    pub fn shift(self) -> Self {
+        self.modifier(KeyCode::Shift)
+    }
+
+    pub fn super_(self) -> Self {
+        self.modifier(KeyCode::Super)
+    }
+
+    // For the right-hand modifiers, anything else fails the chord
+    pub fn modifier(mut self, key: KeyCode) -> Self {
+        if !key.is_modifier() {
+            self.invalid.get_or_insert(key);
+        } else if !self.modifiers.contains(&key) {
+            self.modifiers.push(key);
+            self.modifiers.sort_by_key(rank);
+        }
+
+        self
+    }
+
+    pub fn key(self, key: KeyCode) -> Result<Chord> {
+        if key.is_modifier() {
+            return Err(Error::Chord(format!(
+                "{key} is a modifier, add it with `modifier`"
+            )));
+        }
+
+        self.finish(Some(key))
+    }
+
+    // Modifiers on their own, such as tapping Super
+    pub fn build(self) -> Result<Chord> {
+        if self.modifiers.is_empty() {
+            return Err(Error::Chord("no keys".to_owned()));
+        }
+
+        self.finish(None)
+    }
+
+    fn finish(self, key: Option<KeyCode>) -> Result<Chord> {
+        if let Some(invalid) = self.invalid {
+            return Err(Error::Chord(format!("{invalid} is not a modifier")));
+        }
+
+        Ok(Chord {
+            modifiers: self.modifiers,
+            key,
+        })
+    }
+}
+
+fn rank(key: &KeyCode) -> u8 {
+    match key {
+        KeyCode::Control => 0,
+        KeyCode::RControl => 1,
+        KeyCode::Alt => 2,
+        KeyCode::RAlt => 3,
+        KeyCode::Shift => 4,
+        KeyCode::RShift => 5,
+        KeyCode::Super => 6,
+        KeyCode::RSuper => 7,
+        _ => u8::MAX,
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.keys().iter().map(KeyCode::name).collect();
+        f.write_str(&names.join("+"))
+    }
+}
+
+// `Ctrl+Shift+T`, key names as `KeyCode` parses them, in any order
+impl FromStr for Chord {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut builder = ChordBuilder::default();
+        let mut key = None;
+
+        for name in s.split('+').map(str::trim) {
+            if name.is_empty() {
+                return Err(Error::Chord(format!("empty key in {s:?}")));
+            }
+
+            let parsed: KeyCode = name.parse().map_err(Error::Chord)?;
+            if parsed.is_modifier() {
+                builder = builder.modifier(parsed);
+            } else if let Some(key) = key {
+                return Err(Error::Chord(format!(
+                    "{key} and {parsed} are both non-modifier keys"
+                )));
+            } else {
+                key = Some(parsed);
+            }
+        }
+
+        match key {
+            Some(key) => builder.key(key),
+            None => builder.build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::key;
+
+    #[test]
+    fn modifiers_are_pressed_in_a_fixed_order() {
+        let chord = Chord::super_()
+            .shift()
+            .modifier(KeyCode::RAlt)
+            .ctrl()
+            .key(KeyCode::T)
+            .unwrap();
+
+        assert_eq!(
+            chord.keys(),
+            [
+                KeyCode::Control,
+                KeyCode::RAlt,
+                KeyCode::Shift,
+                KeyCode::Super,
+                KeyCode::T,
+            ]
+        );
+        assert_eq!(
+            chord,
+            Chord::ctrl()
+                .modifier(KeyCode::RAlt)
+                .shift()
+                .super_()
+                .key(KeyCode::T)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn duplicate_modifiers_are_pressed_once() {
+        let chord = Chord::ctrl().ctrl().shift().ctrl().key(KeyCode::C).unwrap();
+
+        assert_eq!(chord.modifiers(), [KeyCode::Control, KeyCode::Shift]);
+        assert_eq!(chord, "Shift+Ctrl+C+Ctrl".parse().unwrap());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let chords = [
+            Chord::ctrl().alt().key(KeyCode::Delete).unwrap(),
+            Chord::super_().build().unwrap(),
+            Chord::new(KeyCode::F4).unwrap(),
+            Chord::shift()
+                .modifier(KeyCode::RControl)
+                .key(KeyCode::Tab)
+                .unwrap(),
+        ];
+
+        for chord in chords {
+            assert_eq!(
+                chord.to_string().parse::<Chord>().unwrap(),
+                chord,
+                "{chord}"
+            );
+        }
+        assert_eq!(
+            "shift + ctrl + t".parse::<Chord>().unwrap().to_string(),
+            "Ctrl+Shift+T"
+        );
+    }
+
+    #[test]
+    fn packet_releases_in_reverse_order() {
+        let chord = Chord::ctrl().key(KeyCode::C).unwrap();
+
+        assert_eq!(
+            chord.packet(),
+            [
+                key::press(&KeyCode::Control),
+                key::press(&KeyCode::C),
+                key::release(&KeyCode::C),
+                key::release(&KeyCode::Control),
+            ]
+        );
+    }
+
+    #[test]
+    fn builder_errors() {
+        // A non-modifier passed as a modifier
+        assert!(matches!(
+            Chord::builder().modifier(KeyCode::A).key(KeyCode::B),
+            Err(Error::Chord(_))
+        ));
+        // A modifier passed as the key
+        assert!(matches!(
+            Chord::ctrl().key(KeyCode::Shift),
+            Err(Error::Chord(_))
+        ));
+        // Nothing to press
+        assert!(matches!(Chord::builder().build(), Err(Error::Chord(_))));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for input in ["", "Ctrl++T", "Ctrl+A+B", "Ctrl+Nope"] {
+            assert!(
+                matches!(input.parse::<Chord>(), Err(Error::Chord(_))),
+                "{input:?}"
+            );
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use crate::action::{key_map::KEY_MAP, KeyCode};
+use crate::{
+    action::{key_map::KEY_MAP, HostLayout, KeyCode, UnicodeInput},
+    error::{Error, Result},
+};
+use std::fmt;
 
 #[macro_export]
 macro_rules! message {
     ($data:expr) => {
         $crate::action::key::create_message($data)
     };
+    ($data:expr, $encoding:expr) => {
+        $crate::action::key::create_message_with($data, $encoding)
+    };
 }
 
 #[macro_export]
@@ -14,24 +21,95 @@ macro_rules! command {
     }};
 }
 
-macro_rules! key {
-    ($($key_code: expr),*) => {{
-        create_text(vec![$($key_code),*])
-    }};
+// How text is turned into keys for a given target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Encoding {
+    pub layout: HostLayout,
+    // Used for characters the layout has no key for
+    pub unicode: UnicodeInput,
+    // What to do with characters neither of them can type
+    pub unsupported: UnsupportedPolicy,
 }
-pub(crate) use key;
 
-macro_rules! shift {
-    ($($key_code: expr),*) => {{
-        command![$(KeyCode::Shift, $key_code),*]
-    }};
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnsupportedPolicy {
+    #[default]
+    Fail,
+    Skip,
+    // Types this instead, fails if it can't be typed either
+    Replace(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedChar {
+    pub char: char,
+    pub char_offset: usize,
+    pub byte_offset: usize,
 }
-pub(crate) use shift;
 
+impl fmt::Display for UnsupportedChar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} (U+{:04X}) at char {}, byte {}",
+            self.char, self.char as u32, self.char_offset, self.byte_offset
+        )
+    }
+}
+
+impl From<HostLayout> for Encoding {
+    fn from(layout: HostLayout) -> Self {
+        Self {
+            layout,
+            ..Default::default()
+        }
+    }
+}
+
+// Types `data` as if the target uses a US QWERTY layout, panics on anything
+// that can't be typed
 pub fn create_message(data: &str) -> Vec<u8> {
-    data.chars()
-        .flat_map(|char| character_packet(char).unwrap())
-        .collect()
+    create_message_with(data, Encoding::default())
+}
+
+pub fn create_message_with(data: &str, encoding: impl Into<Encoding>) -> Vec<u8> {
+    match try_create_message_with(data, encoding) {
+        Ok(packet) => packet,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+pub fn try_create_message(data: &str) -> Result<Vec<u8>> {
+    try_create_message_with(data, Encoding::default())
+}
+
+// Reports every character that can't be typed, not only the first
+pub fn try_create_message_with(data: &str, encoding: impl Into<Encoding>) -> Result<Vec<u8>> {
+    let encoding = encoding.into();
+    let mut packet = vec![];
+    let mut unsupported = vec![];
+
+    for (char_offset, (byte_offset, char)) in data.char_indices().enumerate() {
+        let encoded = character_packet(char, encoding).or_else(|| match encoding.unsupported {
+            UnsupportedPolicy::Fail => None,
+            UnsupportedPolicy::Skip => Some(vec![]),
+            UnsupportedPolicy::Replace(replacement) => character_packet(replacement, encoding),
+        });
+
+        match encoded {
+            Some(encoded) => packet.extend(encoded),
+            None => unsupported.push(UnsupportedChar {
+                char,
+                char_offset,
+                byte_offset,
+            }),
+        }
+    }
+
+    match unsupported.is_empty() {
+        true => Ok(packet),
+        false => Err(Error::UnsupportedChars(unsupported)),
+    }
 }
 
 pub fn create_command(keys: Vec<KeyCode>) -> Vec<u8> {
@@ -49,125 +127,101 @@ pub fn create_command(keys: Vec<KeyCode>) -> Vec<u8> {
     //     .collect()
 }
 
-fn create_text(keys: Vec<KeyCode>) -> Vec<u8> {
-    keys.iter()
-        .flat_map(|keycode| vec![press(keycode), release(keycode)])
-        .collect()
+fn character_packet(char: char, encoding: Encoding) -> Option<Vec<u8>> {
+    match encoding.layout.strokes(char) {
+        Some(strokes) => Some(
+            strokes
+                .iter()
+                .flat_map(|stroke| create_command(stroke.keys()))
+                .collect(),
+        ),
+        None => encoding.unicode.packet(char, encoding.layout),
+    }
 }
 
-fn character_packet(char: char) -> Result<Vec<u8>, String> {
-    let packet = match char {
-        '`' => key!(KeyCode::Tilde),
-        '~' => shift!(KeyCode::Tilde),
-        '1' => key!(KeyCode::One),
-        '!' => shift!(KeyCode::One),
-        '2' => key!(KeyCode::Two),
-        '@' => shift!(KeyCode::Two),
-        '3' => key!(KeyCode::Three),
-        '#' => shift!(KeyCode::Three),
-        '4' => key!(KeyCode::Four),
-        '$' => shift!(KeyCode::Four),
-        '5' => key!(KeyCode::Five),
-        '%' => shift!(KeyCode::Five),
-        '6' => key!(KeyCode::Six),
-        '^' => shift!(KeyCode::Six),
-        '7' => key!(KeyCode::Seven),
-        '&' => shift!(KeyCode::Seven),
-        '8' => key!(KeyCode::Eight),
-        '*' => shift!(KeyCode::Eight),
-        '9' => key!(KeyCode::Nine),
-        '(' => shift!(KeyCode::Nine),
-        '0' => key!(KeyCode::Zero),
-        ')' => shift!(KeyCode::Zero),
-        '-' => key!(KeyCode::Dash),
-        '_' => shift!(KeyCode::Dash),
-        '=' => key!(KeyCode::Equal),
-        '+' => shift!(KeyCode::Equal),
-
-        'a' => key!(KeyCode::A),
-        'A' => shift!(KeyCode::A),
-        'b' => key!(KeyCode::B),
-        'B' => shift!(KeyCode::B),
-        'c' => key!(KeyCode::C),
-        'C' => shift!(KeyCode::C),
-        'd' => key!(KeyCode::D),
-        'D' => shift!(KeyCode::D),
-        'e' => key!(KeyCode::E),
-        'E' => shift!(KeyCode::E),
-        'f' => key!(KeyCode::F),
-        'F' => shift!(KeyCode::F),
-        'g' => key!(KeyCode::G),
-        'G' => shift!(KeyCode::G),
-        'h' => key!(KeyCode::H),
-        'H' => shift!(KeyCode::H),
-        'i' => key!(KeyCode::I),
-        'I' => shift!(KeyCode::I),
-        'j' => key!(KeyCode::J),
-        'J' => shift!(KeyCode::J),
-        'k' => key!(KeyCode::K),
-        'K' => shift!(KeyCode::K),
-        'l' => key!(KeyCode::L),
-        'L' => shift!(KeyCode::L),
-        'm' => key!(KeyCode::M),
-        'M' => shift!(KeyCode::M),
-        'n' => key!(KeyCode::N),
-        'N' => shift!(KeyCode::N),
-        'o' => key!(KeyCode::O),
-        'O' => shift!(KeyCode::O),
-        'p' => key!(KeyCode::P),
-        'P' => shift!(KeyCode::P),
-        'q' => key!(KeyCode::Q),
-        'Q' => shift!(KeyCode::Q),
-        'r' => key!(KeyCode::R),
-        'R' => shift!(KeyCode::R),
-        's' => key!(KeyCode::S),
-        'S' => shift!(KeyCode::S),
-        't' => key!(KeyCode::T),
-        'T' => shift!(KeyCode::T),
-        'u' => key!(KeyCode::U),
-        'U' => shift!(KeyCode::U),
-        'v' => key!(KeyCode::V),
-        'V' => shift!(KeyCode::V),
-        'w' => key!(KeyCode::W),
-        'W' => shift!(KeyCode::W),
-        'x' => key!(KeyCode::X),
-        'X' => shift!(KeyCode::X),
-        'y' => key!(KeyCode::Y),
-        'Y' => shift!(KeyCode::Y),
-        'z' => key!(KeyCode::Z),
-        'Z' => shift!(KeyCode::Z),
-
-        '[' => key!(KeyCode::LBracket),
-        '{' => shift!(KeyCode::LBracket),
-        ']' => key!(KeyCode::RBracket),
-        '}' => shift!(KeyCode::RBracket),
-        '\\' => key!(KeyCode::BackSlash),
-        '|' => shift!(KeyCode::BackSlash),
-        ';' => key!(KeyCode::SemiColon),
-        ':' => shift!(KeyCode::SemiColon),
-        '\'' => key!(KeyCode::Quote),
-        '\"' => shift!(KeyCode::Quote),
-        ',' => key!(KeyCode::Comma),
-        '<' => shift!(KeyCode::Comma),
-        '.' => key!(KeyCode::Period),
-        '>' => shift!(KeyCode::Period),
-        '/' => key!(KeyCode::ForwardSlash),
-        '?' => shift!(KeyCode::ForwardSlash),
-
-        ' ' => command!(KeyCode::Space),
-        '\n' | '\r' => command!(KeyCode::Enter),
-        '\t' => command!(KeyCode::Tab),
-
-        char => return Err(format!("Unimplemented: Char({char})")),
-    };
-
-    Ok(packet)
-}
-
-fn press(keycode: &KeyCode) -> u8 {
+pub(crate) fn press(keycode: &KeyCode) -> u8 {
     KEY_MAP.get(keycode).unwrap().0
 }
 
-fn release(keycode: &KeyCode) -> u8 {
+pub(crate) fn release(keycode: &KeyCode) -> u8 {
     KEY_MAP.get(keycode).unwrap().1
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsupported(data: &str, encoding: impl Into<Encoding>) -> Vec<UnsupportedChar> {
+        match try_create_message_with(data, encoding) {
+            Err(Error::UnsupportedChars(chars)) => chars,
+            other => panic!("{data:?} encoded to {other:?}"),
+        }
+    }
+
+    #[test]
+    fn every_unsupported_char_is_reported() {
+        assert_eq!(
+            unsupported("héllo😀", Encoding::default()),
+            [
+                UnsupportedChar {
+                    char: 'é',
+                    char_offset: 1,
+                    byte_offset: 1,
+                },
+                UnsupportedChar {
+                    char: '😀',
+                    char_offset: 5,
+                    byte_offset: 6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn error_message_lists_offsets() {
+        let err = try_create_message("héllo😀").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Can't type 'é' (U+00E9) at char 1, byte 1, '😀' (U+1F600) at char 5, byte 6"
+        );
+    }
+
+    #[test]
+    fn layout_decides_what_is_supported() {
+        assert!(try_create_message_with("é", HostLayout::French).is_ok());
+        assert_eq!(unsupported("aé", HostLayout::German)[0].byte_offset, 1);
+    }
+
+    #[test]
+    fn skip_drops_unsupported_chars() {
+        let encoding = Encoding {
+            unsupported: UnsupportedPolicy::Skip,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            try_create_message_with("héllo😀", encoding).unwrap(),
+            create_message("hllo")
+        );
+    }
+
+    #[test]
+    fn replace_types_the_replacement() {
+        let encoding = Encoding {
+            unsupported: UnsupportedPolicy::Replace('?'),
+            ..Default::default()
+        };
+        assert_eq!(
+            try_create_message_with("hé", encoding).unwrap(),
+            create_message("h?")
+        );
+
+        // The replacement itself has to be typeable
+        let encoding = Encoding {
+            unsupported: UnsupportedPolicy::Replace('€'),
+            ..Default::default()
+        };
+        assert_eq!(unsupported("hé", encoding).len(), 1);
+    }
+}
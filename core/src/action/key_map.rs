@@ -1,6 +1,6 @@
 use lazy_static::lazy_static;
-use num_enum::{TryFromPrimitive, UnsafeFromPrimitive};
-use std::collections::HashMap;
+use num_enum::TryFromPrimitive;
+use std::{collections::HashMap, fmt, str::FromStr};
 
 macro_rules! key_map {
     ($($key:expr => $val:expr),*) => {{
@@ -90,12 +90,58 @@ lazy_static! {
         KeyCode::F9 => (120, 248),
         KeyCode::F10 => (121, 249),
         KeyCode::F11 => (122, 250),
-        KeyCode::F12 => (123, 251)
+        KeyCode::F12 => (123, 251),
+
+        KeyCode::RShift => (57, 185),
+        KeyCode::RControl => (64, 192),
+        KeyCode::RAlt => (62, 190),
+        KeyCode::RSuper => (71, 199),
+        KeyCode::Menu => (72, 200),
+
+        KeyCode::Insert => (75, 203),
+        KeyCode::Delete => (76, 204),
+        KeyCode::Home => (80, 208),
+        KeyCode::End => (81, 209),
+        KeyCode::PageUp => (85, 213),
+        KeyCode::PageDown => (86, 214),
+
+        KeyCode::PrintScreen => (124, 252),
+        KeyCode::ScrollLock => (125, 253),
+        KeyCode::Pause => (126, 254),
+        KeyCode::NumLock => (90, 218),
+
+        KeyCode::KeypadZero => (99, 227),
+        KeyCode::KeypadOne => (93, 221),
+        KeyCode::KeypadTwo => (98, 226),
+        KeyCode::KeypadThree => (103, 231),
+        KeyCode::KeypadFour => (92, 220),
+        KeyCode::KeypadFive => (97, 225),
+        KeyCode::KeypadSix => (102, 230),
+        KeyCode::KeypadSeven => (91, 219),
+        KeyCode::KeypadEight => (96, 224),
+        KeyCode::KeypadNine => (101, 229),
+        KeyCode::KeypadDivide => (95, 223),
+        KeyCode::KeypadMultiply => (100, 228),
+        KeyCode::KeypadMinus => (105, 233),
+        KeyCode::KeypadPlus => (106, 234),
+        KeyCode::KeypadEnter => (108, 236),
+        KeyCode::KeypadPeriod => (104, 232),
+
+        // ISO keyboards only, left of Z and left of Enter. AT-101 key
+        // positions 45 and 42, as listed in the USB HID usage tables.
+        KeyCode::NonUsBackSlash => (45, 173),
+        KeyCode::NonUsHash => (42, 170)
     };
+
+    // Code => (Key, Pressed)
+    pub(crate) static ref CODE_MAP: HashMap<u8, (KeyCode, bool)> = KEY_MAP
+        .iter()
+        .flat_map(|(key, (press, release))| [(*press, (*key, true)), (*release, (*key, false))])
+        .collect();
 }
 
 #[repr(u8)]
-#[derive(Hash, PartialEq, Eq, UnsafeFromPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, TryFromPrimitive)]
 pub enum KeyCode {
     Zero,
     One,
@@ -173,4 +219,297 @@ pub enum KeyCode {
     F10,
     F11,
     F12,
+
+    // Appended so the discriminants above stay stable. There are no F13-F24,
+    // the device has no key numbers for them.
+    RShift,
+    RControl,
+    RAlt,
+    RSuper,
+    Menu,
+
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+
+    PrintScreen,
+    ScrollLock,
+    Pause,
+    NumLock,
+
+    KeypadZero,
+    KeypadOne,
+    KeypadTwo,
+    KeypadThree,
+    KeypadFour,
+    KeypadFive,
+    KeypadSix,
+    KeypadSeven,
+    KeypadEight,
+    KeypadNine,
+    KeypadDivide,
+    KeypadMultiply,
+    KeypadMinus,
+    KeypadPlus,
+    KeypadEnter,
+    KeypadPeriod,
+
+    NonUsBackSlash,
+    NonUsHash,
+}
+
+impl KeyCode {
+    pub const COUNT: u8 = Self::NonUsHash as u8 + 1;
+
+    pub fn is_modifier(&self) -> bool {
+        matches!(
+            self,
+            Self::Shift
+                | Self::Control
+                | Self::Alt
+                | Self::Super
+                | Self::RShift
+                | Self::RControl
+                | Self::RAlt
+                | Self::RSuper
+        )
+    }
+
+    pub fn all() -> impl Iterator<Item = KeyCode> {
+        (0..Self::COUNT).filter_map(|code| KeyCode::try_from(code).ok())
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Zero => "0".to_owned(),
+            Self::One => "1".to_owned(),
+            Self::Two => "2".to_owned(),
+            Self::Three => "3".to_owned(),
+            Self::Four => "4".to_owned(),
+            Self::Five => "5".to_owned(),
+            Self::Six => "6".to_owned(),
+            Self::Seven => "7".to_owned(),
+            Self::Eight => "8".to_owned(),
+            Self::Nine => "9".to_owned(),
+            Self::Control => "Ctrl".to_owned(),
+            Self::RControl => "RCtrl".to_owned(),
+            key => format!("{key:?}"),
+        }
+    }
+}
+
+impl fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+// Case-insensitive, takes the variant name, what `Display` prints, the
+// punctuation a key types without Shift on US QWERTY and common aliases
+impl FromStr for KeyCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+        if let Some(key) = KeyCode::all().find(|key| {
+            key.name().to_ascii_lowercase() == name
+                || format!("{key:?}").to_ascii_lowercase() == name
+        }) {
+            return Ok(key);
+        }
+
+        ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, key)| *key)
+            .ok_or_else(|| format!("unknown key {s:?}"))
+    }
+}
+
+const ALIASES: &[(&str, KeyCode)] = &[
+    ("`", KeyCode::Tilde),
+    ("grave", KeyCode::Tilde),
+    ("backtick", KeyCode::Tilde),
+    ("-", KeyCode::Dash),
+    ("minus", KeyCode::Dash),
+    ("=", KeyCode::Equal),
+    ("equals", KeyCode::Equal),
+    ("[", KeyCode::LBracket),
+    ("]", KeyCode::RBracket),
+    ("\\", KeyCode::BackSlash),
+    (";", KeyCode::SemiColon),
+    ("'", KeyCode::Quote),
+    (",", KeyCode::Comma),
+    (".", KeyCode::Period),
+    ("/", KeyCode::ForwardSlash),
+    ("slash", KeyCode::ForwardSlash),
+    ("bs", KeyCode::BackSpace),
+    ("capslock", KeyCode::Caps),
+    ("return", KeyCode::Enter),
+    ("cr", KeyCode::Enter),
+    ("control", KeyCode::Control),
+    ("lctrl", KeyCode::Control),
+    ("lshift", KeyCode::Shift),
+    ("lalt", KeyCode::Alt),
+    ("option", KeyCode::Alt),
+    ("opt", KeyCode::Alt),
+    ("altgr", KeyCode::RAlt),
+    ("win", KeyCode::Super),
+    ("windows", KeyCode::Super),
+    ("meta", KeyCode::Super),
+    ("cmd", KeyCode::Super),
+    ("command", KeyCode::Super),
+    ("lsuper", KeyCode::Super),
+    ("lwin", KeyCode::Super),
+    ("rwin", KeyCode::RSuper),
+    ("esc", KeyCode::Escape),
+    ("apps", KeyCode::Menu),
+    ("appskey", KeyCode::Menu),
+    ("ins", KeyCode::Insert),
+    ("del", KeyCode::Delete),
+    ("pgup", KeyCode::PageUp),
+    ("pgdn", KeyCode::PageDown),
+    ("prtsc", KeyCode::PrintScreen),
+    ("printscr", KeyCode::PrintScreen),
+    ("scrlk", KeyCode::ScrollLock),
+    ("break", KeyCode::Pause),
+    ("numlk", KeyCode::NumLock),
+    ("kp0", KeyCode::KeypadZero),
+    ("kp1", KeyCode::KeypadOne),
+    ("kp2", KeyCode::KeypadTwo),
+    ("kp3", KeyCode::KeypadThree),
+    ("kp4", KeyCode::KeypadFour),
+    ("kp5", KeyCode::KeypadFive),
+    ("kp6", KeyCode::KeypadSix),
+    ("kp7", KeyCode::KeypadSeven),
+    ("kp8", KeyCode::KeypadEight),
+    ("kp9", KeyCode::KeypadNine),
+    ("kpdivide", KeyCode::KeypadDivide),
+    ("kpmultiply", KeyCode::KeypadMultiply),
+    ("kpminus", KeyCode::KeypadMinus),
+    ("kpplus", KeyCode::KeypadPlus),
+    ("kpenter", KeyCode::KeypadEnter),
+    ("kpperiod", KeyCode::KeypadPeriod),
+    ("numpad0", KeyCode::KeypadZero),
+    ("numpad1", KeyCode::KeypadOne),
+    ("numpad2", KeyCode::KeypadTwo),
+    ("numpad3", KeyCode::KeypadThree),
+    ("numpad4", KeyCode::KeypadFour),
+    ("numpad5", KeyCode::KeypadFive),
+    ("numpad6", KeyCode::KeypadSix),
+    ("numpad7", KeyCode::KeypadSeven),
+    ("numpad8", KeyCode::KeypadEight),
+    ("numpad9", KeyCode::KeypadNine),
+    ("numpaddiv", KeyCode::KeypadDivide),
+    ("numpadmult", KeyCode::KeypadMultiply),
+    ("numpadsub", KeyCode::KeypadMinus),
+    ("numpadadd", KeyCode::KeypadPlus),
+    ("numpadenter", KeyCode::KeypadEnter),
+    ("numpaddot", KeyCode::KeypadPeriod),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FLUSH;
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_key_is_mapped() {
+        assert_eq!(KeyCode::all().count(), KeyCode::COUNT as usize);
+        for key in KeyCode::all() {
+            assert!(KEY_MAP.contains_key(&key), "{key:?} has no key number");
+        }
+        assert_eq!(KEY_MAP.len(), KeyCode::COUNT as usize);
+    }
+
+    #[test]
+    fn release_is_press_plus_128() {
+        for (key, (press, release)) in KEY_MAP.iter() {
+            assert!(*press < 128, "{key:?}");
+            assert_eq!(*release, press + 128, "{key:?}");
+        }
+    }
+
+    #[test]
+    fn key_numbers_are_unique_and_not_reserved() {
+        let mut seen = HashSet::new();
+        for (key, (press, _)) in KEY_MAP.iter() {
+            assert!(seen.insert(*press), "{key:?} reuses {press}");
+            assert_ne!(*press, 0, "{key:?} collides with the mouse frame");
+            assert_ne!(*press, FLUSH, "{key:?} collides with flush");
+        }
+    }
+
+    #[test]
+    fn code_map_inverts_key_map() {
+        for (key, (press, release)) in KEY_MAP.iter() {
+            assert_eq!(CODE_MAP[press], (*key, true));
+            assert_eq!(CODE_MAP[release], (*key, false));
+        }
+    }
+
+    // Key numbers are AT-101 key positions
+    #[test]
+    fn key_numbers_match_the_table() {
+        let expected = [
+            (KeyCode::Tilde, 1),
+            (KeyCode::BackSpace, 15),
+            (KeyCode::Caps, 30),
+            (KeyCode::Enter, 43),
+            (KeyCode::Shift, 44),
+            (KeyCode::Control, 58),
+            (KeyCode::Space, 61),
+            (KeyCode::Escape, 110),
+            (KeyCode::F1, 112),
+            (KeyCode::F12, 123),
+            (KeyCode::NonUsHash, 42),
+            (KeyCode::NonUsBackSlash, 45),
+            (KeyCode::Insert, 75),
+            (KeyCode::Delete, 76),
+            (KeyCode::Home, 80),
+            (KeyCode::End, 81),
+            (KeyCode::PageUp, 85),
+            (KeyCode::PageDown, 86),
+            (KeyCode::NumLock, 90),
+            (KeyCode::KeypadSeven, 91),
+            (KeyCode::KeypadZero, 99),
+            (KeyCode::KeypadEnter, 108),
+            (KeyCode::PrintScreen, 124),
+            (KeyCode::ScrollLock, 125),
+            (KeyCode::Pause, 126),
+            (KeyCode::RShift, 57),
+            (KeyCode::RControl, 64),
+            (KeyCode::RAlt, 62),
+        ];
+        for (key, press) in expected {
+            assert_eq!(KEY_MAP[&key].0, press, "{key:?}");
+        }
+    }
+
+    #[test]
+    fn existing_discriminants_are_stable() {
+        assert_eq!(KeyCode::Zero as u8, 0);
+        assert_eq!(KeyCode::A as u8, 10);
+        assert_eq!(KeyCode::Shift as u8, 52);
+        assert_eq!(KeyCode::F12 as u8, 72);
+        assert_eq!(KeyCode::RShift as u8, 73);
+        assert_eq!(KeyCode::NonUsHash as u8, KeyCode::COUNT - 1);
+    }
+
+    #[test]
+    fn right_hand_modifiers() {
+        for key in [
+            KeyCode::RShift,
+            KeyCode::RControl,
+            KeyCode::RAlt,
+            KeyCode::RSuper,
+        ] {
+            assert!(key.is_modifier(), "{key:?}");
+        }
+        assert!(!KeyCode::Menu.is_modifier());
+    }
 }
@@ -0,0 +1,551 @@
+// Which keys the target's keyboard layout needs to produce a character. The
+// tables list what each key types on its own, with Shift, with AltGr and with
+// Shift+AltGr, `\0` marking nothing. Dead keys are typed followed by Space so
+// they come out on their own instead of combining with the next character.
+
+use super::KeyCode;
+use lazy_static::lazy_static;
+use num_enum::TryFromPrimitive;
+use std::{collections::HashMap, fmt, str::FromStr};
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, TryFromPrimitive)]
+pub enum HostLayout {
+    #[default]
+    UsQwerty,
+    Uk,
+    German,
+    French,
+    Swiss,
+    Nordic,
+    Dvorak,
+}
+
+impl HostLayout {
+    pub const ALL: [HostLayout; 7] = [
+        Self::UsQwerty,
+        Self::Uk,
+        Self::German,
+        Self::French,
+        Self::Swiss,
+        Self::Nordic,
+        Self::Dvorak,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UsQwerty => "us",
+            Self::Uk => "uk",
+            Self::German => "de",
+            Self::French => "fr",
+            Self::Swiss => "ch",
+            Self::Nordic => "nordic",
+            Self::Dvorak => "dvorak",
+        }
+    }
+
+    // Keystrokes typing `char` in order, None if the layout can't produce it
+    pub fn strokes(&self, char: char) -> Option<&'static [Stroke]> {
+        LAYOUTS[self].get(&char).map(Vec::as_slice)
+    }
+
+    fn table(&self) -> &'static Table {
+        match self {
+            Self::UsQwerty => &US,
+            Self::Uk => &UK,
+            Self::German => &GERMAN,
+            Self::French => &FRENCH,
+            Self::Swiss => &SWISS,
+            Self::Nordic => &NORDIC,
+            Self::Dvorak => &DVORAK,
+        }
+    }
+}
+
+impl fmt::Display for HostLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HostLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown layout {s:?}"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stroke {
+    pub key: KeyCode,
+    pub shift: bool,
+    pub altgr: bool,
+}
+
+impl Stroke {
+    const fn new(key: KeyCode, level: usize) -> Self {
+        Self {
+            key,
+            shift: level % 2 == 1,
+            altgr: level >= 2,
+        }
+    }
+
+    // Modifiers go down first and come up last. AltGr is the right Alt key.
+    pub fn keys(&self) -> Vec<KeyCode> {
+        let mut keys = vec![];
+        if self.shift {
+            keys.push(KeyCode::Shift);
+        }
+        if self.altgr {
+            keys.push(KeyCode::RAlt);
+        }
+        keys.push(self.key);
+
+        keys
+    }
+}
+
+struct Table {
+    // Key, what it types at each level
+    keys: &'static [(KeyCode, &'static str)],
+    // Character, key, level
+    dead: &'static [(char, KeyCode, usize)],
+}
+
+const BASE: usize = 0;
+const SHIFT: usize = 1;
+const ALTGR: usize = 2;
+
+// Letters sit where QWERTY has them unless a layout says otherwise
+const LETTERS: [(KeyCode, char); 26] = [
+    (KeyCode::A, 'a'),
+    (KeyCode::B, 'b'),
+    (KeyCode::C, 'c'),
+    (KeyCode::D, 'd'),
+    (KeyCode::E, 'e'),
+    (KeyCode::F, 'f'),
+    (KeyCode::G, 'g'),
+    (KeyCode::H, 'h'),
+    (KeyCode::I, 'i'),
+    (KeyCode::J, 'j'),
+    (KeyCode::K, 'k'),
+    (KeyCode::L, 'l'),
+    (KeyCode::M, 'm'),
+    (KeyCode::N, 'n'),
+    (KeyCode::O, 'o'),
+    (KeyCode::P, 'p'),
+    (KeyCode::Q, 'q'),
+    (KeyCode::R, 'r'),
+    (KeyCode::S, 's'),
+    (KeyCode::T, 't'),
+    (KeyCode::U, 'u'),
+    (KeyCode::V, 'v'),
+    (KeyCode::W, 'w'),
+    (KeyCode::X, 'x'),
+    (KeyCode::Y, 'y'),
+    (KeyCode::Z, 'z'),
+];
+
+const US: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "`~"),
+        (KeyCode::One, "1!"),
+        (KeyCode::Two, "2@"),
+        (KeyCode::Three, "3#"),
+        (KeyCode::Four, "4$"),
+        (KeyCode::Five, "5%"),
+        (KeyCode::Six, "6^"),
+        (KeyCode::Seven, "7&"),
+        (KeyCode::Eight, "8*"),
+        (KeyCode::Nine, "9("),
+        (KeyCode::Zero, "0)"),
+        (KeyCode::Dash, "-_"),
+        (KeyCode::Equal, "=+"),
+        (KeyCode::LBracket, "[{"),
+        (KeyCode::RBracket, "]}"),
+        (KeyCode::BackSlash, "\\|"),
+        (KeyCode::SemiColon, ";:"),
+        (KeyCode::Quote, "'\""),
+        (KeyCode::Comma, ",<"),
+        (KeyCode::Period, ".>"),
+        (KeyCode::ForwardSlash, "/?"),
+    ],
+    dead: &[],
+};
+
+const UK: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "`¬¦"),
+        (KeyCode::One, "1!"),
+        (KeyCode::Two, "2\""),
+        (KeyCode::Three, "3£"),
+        (KeyCode::Four, "4$€"),
+        (KeyCode::Five, "5%"),
+        (KeyCode::Six, "6^"),
+        (KeyCode::Seven, "7&"),
+        (KeyCode::Eight, "8*"),
+        (KeyCode::Nine, "9("),
+        (KeyCode::Zero, "0)"),
+        (KeyCode::Dash, "-_"),
+        (KeyCode::Equal, "=+"),
+        (KeyCode::LBracket, "[{"),
+        (KeyCode::RBracket, "]}"),
+        (KeyCode::SemiColon, ";:"),
+        (KeyCode::Quote, "'@"),
+        (KeyCode::NonUsHash, "#~"),
+        (KeyCode::NonUsBackSlash, "\\|"),
+        (KeyCode::Comma, ",<"),
+        (KeyCode::Period, ".>"),
+        (KeyCode::ForwardSlash, "/?"),
+    ],
+    dead: &[],
+};
+
+const GERMAN: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "\0°"),
+        (KeyCode::One, "1!"),
+        (KeyCode::Two, "2\"²"),
+        (KeyCode::Three, "3§³"),
+        (KeyCode::Four, "4$"),
+        (KeyCode::Five, "5%"),
+        (KeyCode::Six, "6&"),
+        (KeyCode::Seven, "7/{"),
+        (KeyCode::Eight, "8(["),
+        (KeyCode::Nine, "9)]"),
+        (KeyCode::Zero, "0=}"),
+        (KeyCode::Dash, "ß?\\"),
+        (KeyCode::Q, "qQ@"),
+        (KeyCode::E, "eE€"),
+        (KeyCode::Y, "zZ"),
+        (KeyCode::LBracket, "üÜ"),
+        (KeyCode::RBracket, "+*~"),
+        (KeyCode::SemiColon, "öÖ"),
+        (KeyCode::Quote, "äÄ"),
+        (KeyCode::NonUsHash, "#'"),
+        (KeyCode::NonUsBackSlash, "<>|"),
+        (KeyCode::Z, "yY"),
+        (KeyCode::M, "mMµ"),
+        (KeyCode::Comma, ",;"),
+        (KeyCode::Period, ".:"),
+        (KeyCode::ForwardSlash, "-_"),
+    ],
+    dead: &[
+        ('^', KeyCode::Tilde, BASE),
+        ('´', KeyCode::Equal, BASE),
+        ('`', KeyCode::Equal, SHIFT),
+    ],
+};
+
+const FRENCH: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "²"),
+        (KeyCode::One, "&1"),
+        (KeyCode::Two, "é2"),
+        (KeyCode::Three, "\"3#"),
+        (KeyCode::Four, "'4{"),
+        (KeyCode::Five, "(5["),
+        (KeyCode::Six, "-6|"),
+        (KeyCode::Seven, "è7"),
+        (KeyCode::Eight, "_8\\"),
+        (KeyCode::Nine, "ç9^"),
+        (KeyCode::Zero, "à0@"),
+        (KeyCode::Dash, ")°]"),
+        (KeyCode::Equal, "=+}"),
+        (KeyCode::Q, "aA"),
+        (KeyCode::W, "zZ"),
+        (KeyCode::E, "eE€"),
+        (KeyCode::RBracket, "$£¤"),
+        (KeyCode::A, "qQ"),
+        (KeyCode::SemiColon, "mM"),
+        (KeyCode::Quote, "ù%"),
+        (KeyCode::NonUsHash, "*µ"),
+        (KeyCode::NonUsBackSlash, "<>"),
+        (KeyCode::Z, "wW"),
+        (KeyCode::M, ",?"),
+        (KeyCode::Comma, ";."),
+        (KeyCode::Period, ":/"),
+        (KeyCode::ForwardSlash, "!§"),
+    ],
+    dead: &[
+        ('~', KeyCode::Two, ALTGR),
+        ('`', KeyCode::Seven, ALTGR),
+        ('^', KeyCode::LBracket, BASE),
+        ('¨', KeyCode::LBracket, SHIFT),
+    ],
+};
+
+// Swiss German
+const SWISS: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "§°"),
+        (KeyCode::One, "1+¦"),
+        (KeyCode::Two, "2\"@"),
+        (KeyCode::Three, "3*#"),
+        (KeyCode::Four, "4ç"),
+        (KeyCode::Five, "5%"),
+        (KeyCode::Six, "6&¬"),
+        (KeyCode::Seven, "7/|"),
+        (KeyCode::Eight, "8(¢"),
+        (KeyCode::Nine, "9)"),
+        (KeyCode::Zero, "0="),
+        (KeyCode::Dash, "'?"),
+        (KeyCode::E, "eE€"),
+        (KeyCode::Y, "zZ"),
+        (KeyCode::LBracket, "üè["),
+        (KeyCode::RBracket, "\0!]"),
+        (KeyCode::SemiColon, "öé"),
+        (KeyCode::Quote, "äà{"),
+        (KeyCode::NonUsHash, "$£}"),
+        (KeyCode::NonUsBackSlash, "<>\\"),
+        (KeyCode::Z, "yY"),
+        (KeyCode::Comma, ",;"),
+        (KeyCode::Period, ".:"),
+        (KeyCode::ForwardSlash, "-_"),
+    ],
+    dead: &[
+        ('´', KeyCode::Dash, ALTGR),
+        ('^', KeyCode::Equal, BASE),
+        ('`', KeyCode::Equal, SHIFT),
+        ('~', KeyCode::Equal, ALTGR),
+        ('¨', KeyCode::RBracket, BASE),
+    ],
+};
+
+// Swedish and Finnish
+const NORDIC: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "§½"),
+        (KeyCode::One, "1!"),
+        (KeyCode::Two, "2\"@"),
+        (KeyCode::Three, "3#£"),
+        (KeyCode::Four, "4¤$"),
+        (KeyCode::Five, "5%€"),
+        (KeyCode::Six, "6&"),
+        (KeyCode::Seven, "7/{"),
+        (KeyCode::Eight, "8(["),
+        (KeyCode::Nine, "9)]"),
+        (KeyCode::Zero, "0=}"),
+        (KeyCode::Dash, "+?\\"),
+        (KeyCode::E, "eE€"),
+        (KeyCode::LBracket, "åÅ"),
+        (KeyCode::SemiColon, "öÖ"),
+        (KeyCode::Quote, "äÄ"),
+        (KeyCode::NonUsHash, "'*"),
+        (KeyCode::NonUsBackSlash, "<>|"),
+        (KeyCode::M, "mMµ"),
+        (KeyCode::Comma, ",;"),
+        (KeyCode::Period, ".:"),
+        (KeyCode::ForwardSlash, "-_"),
+    ],
+    dead: &[
+        ('´', KeyCode::Equal, BASE),
+        ('`', KeyCode::Equal, SHIFT),
+        ('¨', KeyCode::RBracket, BASE),
+        ('^', KeyCode::RBracket, SHIFT),
+        ('~', KeyCode::RBracket, ALTGR),
+    ],
+};
+
+// US Dvorak
+const DVORAK: Table = Table {
+    keys: &[
+        (KeyCode::Tilde, "`~"),
+        (KeyCode::One, "1!"),
+        (KeyCode::Two, "2@"),
+        (KeyCode::Three, "3#"),
+        (KeyCode::Four, "4$"),
+        (KeyCode::Five, "5%"),
+        (KeyCode::Six, "6^"),
+        (KeyCode::Seven, "7&"),
+        (KeyCode::Eight, "8*"),
+        (KeyCode::Nine, "9("),
+        (KeyCode::Zero, "0)"),
+        (KeyCode::Dash, "[{"),
+        (KeyCode::Equal, "]}"),
+        (KeyCode::Q, "'\""),
+        (KeyCode::W, ",<"),
+        (KeyCode::E, ".>"),
+        (KeyCode::R, "pP"),
+        (KeyCode::T, "yY"),
+        (KeyCode::Y, "fF"),
+        (KeyCode::U, "gG"),
+        (KeyCode::I, "cC"),
+        (KeyCode::O, "rR"),
+        (KeyCode::P, "lL"),
+        (KeyCode::LBracket, "/?"),
+        (KeyCode::RBracket, "=+"),
+        (KeyCode::BackSlash, "\\|"),
+        (KeyCode::A, "aA"),
+        (KeyCode::S, "oO"),
+        (KeyCode::D, "eE"),
+        (KeyCode::F, "uU"),
+        (KeyCode::G, "iI"),
+        (KeyCode::H, "dD"),
+        (KeyCode::J, "hH"),
+        (KeyCode::K, "tT"),
+        (KeyCode::L, "nN"),
+        (KeyCode::SemiColon, "sS"),
+        (KeyCode::Quote, "-_"),
+        (KeyCode::Z, ";:"),
+        (KeyCode::X, "qQ"),
+        (KeyCode::C, "jJ"),
+        (KeyCode::V, "kK"),
+        (KeyCode::B, "xX"),
+        (KeyCode::N, "bB"),
+        (KeyCode::M, "mM"),
+        (KeyCode::Comma, "wW"),
+        (KeyCode::Period, "vV"),
+        (KeyCode::ForwardSlash, "zZ"),
+    ],
+    dead: &[],
+};
+
+lazy_static! {
+    static ref LAYOUTS: HashMap<HostLayout, HashMap<char, Vec<Stroke>>> = HostLayout::ALL
+        .into_iter()
+        .map(|layout| (layout, build(layout.table())))
+        .collect();
+}
+
+fn build(table: &Table) -> HashMap<char, Vec<Stroke>> {
+    let mut map = HashMap::new();
+
+    for (key, levels) in table.keys {
+        for (level, char) in levels.chars().enumerate() {
+            if char != '\0' {
+                map.entry(char).or_insert(vec![Stroke::new(*key, level)]);
+            }
+        }
+    }
+
+    for (key, lower) in LETTERS {
+        if table.keys.iter().any(|(remapped, _)| *remapped == key) {
+            continue;
+        }
+        map.entry(lower).or_insert(vec![Stroke::new(key, BASE)]);
+        map.entry(lower.to_ascii_uppercase())
+            .or_insert(vec![Stroke::new(key, SHIFT)]);
+    }
+
+    for (char, key, level) in table.dead {
+        map.entry(*char).or_insert(vec![
+            Stroke::new(*key, *level),
+            Stroke::new(KeyCode::Space, BASE),
+        ]);
+    }
+
+    map.insert(' ', vec![Stroke::new(KeyCode::Space, BASE)]);
+    map.insert('\n', vec![Stroke::new(KeyCode::Enter, BASE)]);
+    map.insert('\r', vec![Stroke::new(KeyCode::Enter, BASE)]);
+    map.insert('\t', vec![Stroke::new(KeyCode::Tab, BASE)]);
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::key::{self, create_message_with};
+
+    fn strokes(layout: HostLayout, char: char) -> Vec<Stroke> {
+        layout
+            .strokes(char)
+            .unwrap_or_else(|| panic!("{layout} can't type {char:?}"))
+            .to_vec()
+    }
+
+    fn dead(key: KeyCode, level: usize) -> Vec<Stroke> {
+        vec![Stroke::new(key, level), Stroke::new(KeyCode::Space, BASE)]
+    }
+
+    #[test]
+    fn us_and_dvorak() {
+        use HostLayout::*;
+
+        assert_eq!(strokes(UsQwerty, '~'), [Stroke::new(KeyCode::Tilde, SHIFT)]);
+        assert_eq!(strokes(UsQwerty, 'Q'), [Stroke::new(KeyCode::Q, SHIFT)]);
+        assert_eq!(strokes(Dvorak, 'q'), [Stroke::new(KeyCode::X, BASE)]);
+        assert_eq!(strokes(Dvorak, '"'), [Stroke::new(KeyCode::Q, SHIFT)]);
+        assert_eq!(UsQwerty.strokes('€'), None);
+    }
+
+    #[test]
+    fn altgr_characters() {
+        use HostLayout::*;
+
+        let expected = [
+            (Uk, '€', KeyCode::Four),
+            (German, '@', KeyCode::Q),
+            (German, '\\', KeyCode::Dash),
+            (French, '@', KeyCode::Zero),
+            (Swiss, '@', KeyCode::Two),
+            (Nordic, '@', KeyCode::Two),
+            (Nordic, '|', KeyCode::NonUsBackSlash),
+        ];
+
+        for (layout, char, key) in expected {
+            assert_eq!(
+                strokes(layout, char),
+                [Stroke::new(key, ALTGR)],
+                "{layout} {char:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn dead_keys_are_followed_by_space() {
+        use HostLayout::*;
+
+        assert_eq!(strokes(German, '^'), dead(KeyCode::Tilde, BASE));
+        assert_eq!(strokes(French, '~'), dead(KeyCode::Two, ALTGR));
+        assert_eq!(strokes(Swiss, '¨'), dead(KeyCode::RBracket, BASE));
+        assert_eq!(strokes(Nordic, '^'), dead(KeyCode::RBracket, SHIFT));
+    }
+
+    #[test]
+    fn remapped_letters() {
+        use HostLayout::*;
+
+        assert_eq!(strokes(German, 'z'), [Stroke::new(KeyCode::Y, BASE)]);
+        assert_eq!(strokes(Swiss, 'Y'), [Stroke::new(KeyCode::Z, SHIFT)]);
+        assert_eq!(strokes(French, 'a'), [Stroke::new(KeyCode::Q, BASE)]);
+        assert_eq!(
+            strokes(French, 'M'),
+            [Stroke::new(KeyCode::SemiColon, SHIFT)]
+        );
+        assert_eq!(strokes(Uk, '@'), [Stroke::new(KeyCode::Quote, SHIFT)]);
+    }
+
+    #[test]
+    fn altgr_is_right_alt_inside_shift() {
+        assert_eq!(
+            create_message_with("@", HostLayout::German),
+            [
+                key::press(&KeyCode::RAlt),
+                key::press(&KeyCode::Q),
+                key::release(&KeyCode::Q),
+                key::release(&KeyCode::RAlt),
+            ]
+        );
+        assert_eq!(
+            Stroke::new(KeyCode::Q, ALTGR + SHIFT).keys(),
+            [KeyCode::Shift, KeyCode::RAlt, KeyCode::Q]
+        );
+    }
+
+    #[test]
+    fn every_layout_types_ascii_letters_and_whitespace() {
+        for layout in HostLayout::ALL {
+            for char in ('a'..='z').chain('A'..='Z').chain(['0', ' ', '\n', '\t']) {
+                assert!(layout.strokes(char).is_some(), "{layout} {char:?}");
+            }
+            assert_eq!(layout.name().parse::<HostLayout>(), Ok(layout));
+        }
+    }
+}
@@ -1,6 +1,17 @@
+mod chord;
 pub mod key;
 mod key_map;
+mod layout;
 mod mouse;
+mod sequence;
+mod typing;
+mod unicode;
 
+pub use chord::{Chord, ChordBuilder};
 pub use key_map::KeyCode;
+pub(crate) use key_map::{CODE_MAP, KEY_MAP};
+pub use layout::{HostLayout, Stroke};
 pub use mouse::{MouseAction, MouseButton, ScrollDirection, ScrollMagnitude};
+pub use sequence::{Sequence, Step};
+pub use typing::{Jitter, Keystroke, TypingProfile};
+pub use unicode::UnicodeInput;
@@ -0,0 +1,542 @@
+// Readable key sequences mixed with literal text
+//
+// - `ctrl+alt+t`    xdotool style, a whole word of modifiers joined by `+`
+// - `<C-S-Esc>`     Vim style, C Ctrl, S Shift, A or M Alt, D Super. A bare
+//                   `<Esc>` needs a key name longer than one character,
+//                   anything else such as `<b>` is typed as is, `<lt>` is `<`
+// - `{Enter}`       AutoHotkey style, also `{Tab 3}` up to 1000 times,
+//                   `{Shift down}`, `{Shift up}` and `{Ctrl+T}`. A single
+//                   character such as `{+}` or `{{}` is typed as is
+// - `^c`, `!{F4}`   AutoHotkey prefixes, ^ Ctrl, ! Alt, + Shift, # Super.
+//                   Only at the start or right after another key, so `a+b`
+//                   and `x^2` are text. Also typed as is when followed by
+//                   whitespace, the end, a character with no key or a
+//                   character running into a word, as in `#include`
+// - `\x`            types x as is
+//
+// Everything else is literal text.
+
+use super::{
+    key::{self, try_create_message_with, Encoding},
+    Chord, KeyCode,
+};
+use crate::error::{Error, Result};
+use std::{ops::Range, str::FromStr};
+
+// Repeats are expanded up front, so `{Key N}` is capped
+pub const MAX_REPEAT: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Text(String),
+    Chord(Chord),
+    KeyDown(KeyCode),
+    KeyUp(KeyCode),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sequence(Vec<Step>);
+
+impl Sequence {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input,
+            pos: 0,
+            steps: vec![],
+            text: String::new(),
+        };
+        parser.run()?;
+        parser.flush_text();
+
+        Ok(Self(parser.steps))
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.0
+    }
+
+    pub fn packet(&self, encoding: impl Into<Encoding>) -> Result<Vec<u8>> {
+        let encoding = encoding.into();
+        let mut packet = vec![];
+
+        for step in &self.0 {
+            match step {
+                Step::Text(text) => packet.extend(try_create_message_with(text, encoding)?),
+                Step::Chord(chord) => packet.extend(chord.packet()),
+                Step::KeyDown(key) => packet.push(key::press(key)),
+                Step::KeyUp(key) => packet.push(key::release(key)),
+            }
+        }
+
+        Ok(packet)
+    }
+}
+
+impl FromStr for Sequence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    steps: Vec<Step>,
+    text: String,
+}
+
+impl Parser<'_> {
+    fn run(&mut self) -> Result<()> {
+        while let Some(char) = self.peek() {
+            match char {
+                '\\' => self.escape()?,
+                '{' => self.brace()?,
+                '<' if self.angle()? => {}
+                '^' | '!' | '+' | '#' if self.after_key() => self.prefixed()?,
+                char if char.is_alphabetic() && self.at_word_start() && self.word_chord()? => {}
+                char => {
+                    self.pos += char.len_utf8();
+                    self.text.push(char);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn escape(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 1;
+
+        match self.peek() {
+            Some(char) => {
+                self.pos += char.len_utf8();
+                self.text.push(char);
+
+                Ok(())
+            }
+            None => Err(error(start..self.pos, "nothing to escape")),
+        }
+    }
+
+    // `{...}` at `pos`
+    fn brace(&mut self) -> Result<()> {
+        let start = self.pos;
+        let (content, end) = braced(self.input, start)?;
+        self.pos = end;
+
+        if content.chars().count() == 1 {
+            self.text.push_str(content);
+            return Ok(());
+        }
+
+        let content_start = start + 1;
+        let mut words = content.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let name_start = content_start + content.find(name).unwrap_or(0);
+        let name_span = name_start..name_start + name.len();
+
+        let argument = words.next();
+        if let Some(extra) = words.next() {
+            let extra_start = content_start + content.rfind(extra).unwrap_or(0);
+            return Err(error(
+                extra_start..extra_start + extra.len(),
+                "unexpected word",
+            ));
+        }
+
+        let Some(argument) = argument else {
+            let chord = parse_chord(name, name_span)?;
+            self.push(Step::Chord(chord));
+            return Ok(());
+        };
+        let argument_start = content_start + content.rfind(argument).unwrap_or(0);
+        let argument_span = argument_start..argument_start + argument.len();
+
+        match argument.to_ascii_lowercase().as_str() {
+            "down" => self.push(Step::KeyDown(parse_key(name, name_span)?)),
+            "up" => self.push(Step::KeyUp(parse_key(name, name_span)?)),
+            count => {
+                let Ok(count) = count.parse::<usize>() else {
+                    return Err(error(argument_span, "expected down, up or a count"));
+                };
+                if count > MAX_REPEAT {
+                    return Err(error(
+                        argument_span,
+                        &format!("count is over the limit of {MAX_REPEAT}"),
+                    ));
+                }
+
+                let chord = parse_chord(name, name_span)?;
+                for _ in 0..count {
+                    self.push(Step::Chord(chord.clone()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // `<...>` at `pos`, false when it isn't key notation and `<` is literal
+    fn angle(&mut self) -> Result<bool> {
+        let start = self.pos;
+        let rest = &self.input[start + 1..];
+        let Some(close) = rest.find('>') else {
+            return Ok(false);
+        };
+
+        let content = &rest[..close];
+        if content.is_empty() || content.contains(|char: char| char.is_whitespace() || char == '<')
+        {
+            return Ok(false);
+        }
+
+        let literal = match content.to_ascii_lowercase().as_str() {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "bar" => Some('|'),
+            "bslash" => Some('\\'),
+            _ => None,
+        };
+        if let Some(literal) = literal {
+            self.text.push(literal);
+            self.pos = start + close + 2;
+            return Ok(true);
+        }
+
+        let mut builder = Chord::builder();
+        let mut name = content;
+        let mut modified = false;
+        while let [modifier, b'-', _, ..] = name.as_bytes() {
+            let modifier = match modifier.to_ascii_uppercase() {
+                b'C' => KeyCode::Control,
+                b'S' => KeyCode::Shift,
+                b'A' | b'M' => KeyCode::Alt,
+                b'D' => KeyCode::Super,
+                _ => break,
+            };
+            builder = builder.modifier(modifier);
+            name = &name[2..];
+            modified = true;
+        }
+
+        let name_start = start + 1 + (content.len() - name.len());
+        let key = match name.parse::<KeyCode>() {
+            Ok(_) if !modified && name.chars().count() == 1 => return Ok(false),
+            Ok(key) => key,
+            Err(_) if !modified => return Ok(false),
+            Err(message) => return Err(error(name_start..name_start + name.len(), &message)),
+        };
+
+        let chord = match key.is_modifier() {
+            true => builder.modifier(key).build(),
+            false => builder.key(key),
+        }
+        .map_err(|err| error(start..start + close + 2, &err.to_string()))?;
+
+        self.push(Step::Chord(chord));
+        self.pos = start + close + 2;
+
+        Ok(true)
+    }
+
+    // A run of `^!+#` and the key they apply to
+    fn prefixed(&mut self) -> Result<()> {
+        let start = self.pos;
+        let mut builder = Chord::builder();
+        let mut end = start;
+
+        for char in self.input[start..].chars() {
+            let modifier = match char {
+                '^' => KeyCode::Control,
+                '!' => KeyCode::Alt,
+                '+' => KeyCode::Shift,
+                '#' => KeyCode::Super,
+                _ => break,
+            };
+            builder = builder.modifier(modifier);
+            end += 1;
+        }
+
+        let key_start = end;
+        let key = match self.input[end..].chars().next() {
+            None => None,
+            Some(char) if char.is_whitespace() => None,
+            Some('{') => {
+                let (content, brace_end) = braced(self.input, end)?;
+                end = brace_end;
+                Some((content, key_start + 1..brace_end - 1))
+            }
+            Some('\\') => {
+                let Some(char) = self.input[end + 1..].chars().next() else {
+                    return Err(error(end..end + 1, "nothing to escape"));
+                };
+                end += 1 + char.len_utf8();
+                Some((&self.input[key_start + 1..end], key_start + 1..end))
+            }
+            Some(char) => {
+                end += char.len_utf8();
+                let name = &self.input[key_start..end];
+                let runs_on = self.input[end..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphanumeric);
+
+                match name.parse::<KeyCode>() {
+                    Ok(_) if !runs_on => Some((name, key_start..end)),
+                    _ => None,
+                }
+            }
+        };
+
+        let Some((name, span)) = key else {
+            self.text.push_str(&self.input[start..end]);
+            self.pos = end;
+            return Ok(());
+        };
+
+        let key = parse_key(name, span.clone())?;
+        let chord = match key.is_modifier() {
+            true => builder.modifier(key).build(),
+            false => builder.key(key),
+        }
+        .map_err(|err| error(span, &err.to_string()))?;
+
+        self.push(Step::Chord(chord));
+        self.pos = end;
+
+        Ok(())
+    }
+
+    // `ctrl+alt+t` as a whole word, false when the word is plain text.
+    // Trailing punctuation is left as text so `ctrl+c,` works in prose
+    fn word_chord(&mut self) -> Result<bool> {
+        let rest = &self.input[self.pos..];
+        let word = &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())];
+
+        let Some(split) = word.rfind('+') else {
+            return Ok(false);
+        };
+        let (modifiers, name) = (&word[..split], &word[split + 1..]);
+        if !modifiers
+            .split('+')
+            .all(|name| matches!(name.parse::<KeyCode>(), Ok(key) if key.is_modifier()))
+        {
+            return Ok(false);
+        }
+
+        let trimmed = name.trim_end_matches(|char: char| char.is_ascii_punctuation());
+        let name = match name.parse::<KeyCode>() {
+            Ok(_) => name,
+            Err(_) if !trimmed.is_empty() => trimmed,
+            Err(_) => return Ok(false),
+        };
+
+        let name_start = self.pos + split + 1;
+        parse_key(name, name_start..name_start + name.len())?;
+
+        let end = split + 1 + name.len();
+        let chord = parse_chord(&word[..end], self.pos..self.pos + end)?;
+        self.push(Step::Chord(chord));
+        self.pos += end;
+
+        Ok(true)
+    }
+
+    // Nothing but keys so far since the start or the last text, which is
+    // flushed whenever a key is pushed
+    fn after_key(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn at_word_start(&self) -> bool {
+        self.input[..self.pos]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn push(&mut self, step: Step) {
+        self.flush_text();
+        self.steps.push(step);
+    }
+
+    fn flush_text(&mut self) {
+        if !self.text.is_empty() {
+            self.steps.push(Step::Text(std::mem::take(&mut self.text)));
+        }
+    }
+}
+
+// Content and end of the braces opening at `start`, `{}}` holds `}`
+fn braced(input: &str, start: usize) -> Result<(&str, usize)> {
+    let rest = &input[start + 1..];
+    if rest.starts_with("}}") {
+        return Ok(("}", start + 3));
+    }
+    if rest.starts_with('}') {
+        return Err(error(start..start + 2, "empty braces"));
+    }
+
+    let skip = rest.chars().next().map_or(0, char::len_utf8);
+    match rest[skip..].find('}') {
+        Some(close) => Ok((&rest[..skip + close], start + 1 + skip + close + 1)),
+        None => Err(error(start..input.len(), "unclosed {")),
+    }
+}
+
+fn parse_key(name: &str, span: Range<usize>) -> Result<KeyCode> {
+    name.parse()
+        .map_err(|message: String| error(span, &message))
+}
+
+fn parse_chord(name: &str, span: Range<usize>) -> Result<Chord> {
+    name.parse().map_err(|err| match err {
+        Error::Chord(message) => error(span, &message),
+        err => error(span, &err.to_string()),
+    })
+}
+
+fn error(span: Range<usize>, message: &str) -> Error {
+    Error::Sequence {
+        start: span.start,
+        end: span.end,
+        message: message.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(input: &str) -> Vec<Step> {
+        Sequence::parse(input).unwrap().steps().to_vec()
+    }
+
+    fn chord(keys: &[KeyCode]) -> Step {
+        let (key, modifiers) = keys.split_last().unwrap();
+        let builder = modifiers
+            .iter()
+            .fold(Chord::builder(), |builder, modifier| {
+                builder.modifier(*modifier)
+            });
+
+        Step::Chord(builder.key(*key).unwrap())
+    }
+
+    fn text(text: &str) -> Step {
+        Step::Text(text.to_owned())
+    }
+
+    fn span(input: &str) -> Range<usize> {
+        match Sequence::parse(input) {
+            Err(Error::Sequence { start, end, .. }) => start..end,
+            other => panic!("{input:?} parsed to {other:?}"),
+        }
+    }
+
+    #[test]
+    fn word_chords() {
+        assert_eq!(
+            steps("open ctrl+alt+t now"),
+            [
+                text("open "),
+                chord(&[KeyCode::Control, KeyCode::Alt, KeyCode::T]),
+                text(" now"),
+            ]
+        );
+        assert_eq!(
+            steps("ctrl+c, then"),
+            [chord(&[KeyCode::Control, KeyCode::C]), text(", then")]
+        );
+    }
+
+    #[test]
+    fn vim_notation() {
+        assert_eq!(
+            steps("<C-S-Esc>"),
+            [chord(&[KeyCode::Control, KeyCode::Shift, KeyCode::Escape])]
+        );
+        assert_eq!(steps("<lt>b>"), [text("<b>")]);
+        assert_eq!(steps("<b>"), [text("<b>")]);
+        assert_eq!(steps("<C-b>"), [chord(&[KeyCode::Control, KeyCode::B])]);
+        assert_eq!(steps("<Tab>"), [chord(&[KeyCode::Tab])]);
+        assert_eq!(steps("a < b"), [text("a < b")]);
+    }
+
+    #[test]
+    fn braces() {
+        assert_eq!(steps("{Tab 3}"), vec![chord(&[KeyCode::Tab]); 3]);
+        assert_eq!(
+            steps("{Shift down}a{Shift up}"),
+            [
+                Step::KeyDown(KeyCode::Shift),
+                text("a"),
+                Step::KeyUp(KeyCode::Shift),
+            ]
+        );
+        assert_eq!(steps("{+}{{}{}}"), [text("+{}")]);
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!(steps("^c"), [chord(&[KeyCode::Control, KeyCode::C])]);
+        assert_eq!(steps("!{F4}"), [chord(&[KeyCode::Alt, KeyCode::F4])]);
+        assert_eq!(steps("1 + 1"), [text("1 + 1")]);
+        assert_eq!(
+            steps("^c^v{Enter}+a"),
+            [
+                chord(&[KeyCode::Control, KeyCode::C]),
+                chord(&[KeyCode::Control, KeyCode::V]),
+                chord(&[KeyCode::Enter]),
+                chord(&[KeyCode::Shift, KeyCode::A]),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefixes_in_text_are_literal() {
+        for input in ["a+b", "x^2", "#include", "^(", "!!", "say hi!", "C#"] {
+            assert_eq!(steps(input), [text(input)], "{input:?}");
+        }
+        assert_eq!(steps("<Esc>a+b"), [chord(&[KeyCode::Escape]), text("a+b")]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(steps(r"\x\{\^"), [text("x{^")]);
+    }
+
+    #[test]
+    fn error_spans() {
+        assert_eq!(span("ab{"), 2..3);
+        assert_eq!(span("<C-Foo>"), 3..6);
+        assert_eq!(span("{Tab many}"), 5..9);
+        assert_eq!(span("{Tab 3 4}"), 7..8);
+        assert_eq!(span(r"a\"), 1..2);
+    }
+
+    #[test]
+    fn repeat_count_is_capped() {
+        assert_eq!(steps("{a 1000}").len(), MAX_REPEAT);
+        assert_eq!(span("x{a 1001}"), 4..8);
+        assert_eq!(span("{a 99999999999999999999999}"), 3..26);
+    }
+
+    #[test]
+    fn packet_matches_chord_packets() {
+        let sequence = Sequence::parse("^c").unwrap();
+        let chord = Chord::ctrl().key(KeyCode::C).unwrap();
+
+        assert_eq!(
+            sequence.packet(Encoding::default()).unwrap(),
+            chord.packet()
+        );
+    }
+}
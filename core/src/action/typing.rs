@@ -0,0 +1,401 @@
+// Human-like timing for typed text
+//
+// Keystrokes are spaced at the profile's words per minute, counting five
+// characters to a word, and each gap is scaled by a random factor drawn from
+// `jitter`. Punctuation and newlines add a pause on top. Typos press a key
+// physically next to the intended one, so they look like slips on any
+// layout, and are corrected with BackSpace right away.
+//
+// The random numbers come from a seeded generator, so the same profile and
+// seed type the same text with the same timing every time.
+
+use super::{
+    key::{self, Encoding},
+    KeyCode, Stroke,
+};
+use crate::error::{Error, Result};
+use std::{f64::consts::TAU, time::Duration};
+
+const CHARS_PER_WORD: f64 = 5.0;
+const PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?'];
+// Gaps are never scaled below or above these, however unlucky the draw
+const MIN_FACTOR: f64 = 0.1;
+const MAX_FACTOR: f64 = 4.0;
+
+const ROWS: [&[KeyCode]; 3] = [
+    &[
+        KeyCode::Q,
+        KeyCode::W,
+        KeyCode::E,
+        KeyCode::R,
+        KeyCode::T,
+        KeyCode::Y,
+        KeyCode::U,
+        KeyCode::I,
+        KeyCode::O,
+        KeyCode::P,
+    ],
+    &[
+        KeyCode::A,
+        KeyCode::S,
+        KeyCode::D,
+        KeyCode::F,
+        KeyCode::G,
+        KeyCode::H,
+        KeyCode::J,
+        KeyCode::K,
+        KeyCode::L,
+    ],
+    &[
+        KeyCode::Z,
+        KeyCode::X,
+        KeyCode::C,
+        KeyCode::V,
+        KeyCode::B,
+        KeyCode::N,
+        KeyCode::M,
+    ],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Jitter {
+    None,
+    // Gaps scaled by a factor spread evenly within 1 ± the given fraction
+    Uniform(f64),
+    // Gaps scaled by a factor around 1 with the given standard deviation
+    Normal(f64),
+}
+
+impl Default for Jitter {
+    fn default() -> Self {
+        Self::Normal(0.2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypingProfile {
+    wpm: f64,
+    jitter: Jitter,
+    punctuation_pause: Duration,
+    newline_pause: Duration,
+    typo_rate: f64,
+    rng: Rng,
+}
+
+// A packet and how long to wait once the device has processed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keystroke {
+    pub packet: Vec<u8>,
+    pub delay: Duration,
+}
+
+impl TypingProfile {
+    pub fn new(wpm: f64) -> Self {
+        Self {
+            wpm,
+            jitter: Jitter::default(),
+            punctuation_pause: Duration::from_millis(250),
+            newline_pause: Duration::from_millis(600),
+            typo_rate: 0.0,
+            rng: Rng(0),
+        }
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    // Extra wait after any of . , ; : ! ?
+    pub fn punctuation_pause(mut self, pause: Duration) -> Self {
+        self.punctuation_pause = pause;
+        self
+    }
+
+    pub fn newline_pause(mut self, pause: Duration) -> Self {
+        self.newline_pause = pause;
+        self
+    }
+
+    // Chance of a typo on each letter, 0.02 mistypes about one in fifty
+    pub fn typos(mut self, rate: f64) -> Self {
+        self.typo_rate = rate;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Rng(seed);
+        self
+    }
+
+    pub fn wpm(&self) -> f64 {
+        self.wpm
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        self.interval()?;
+
+        if let Jitter::Uniform(spread) | Jitter::Normal(spread) = self.jitter {
+            if !spread.is_finite() || spread < 0.0 {
+                return Err(Error::Config(format!("typing jitter {spread}")));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.typo_rate) {
+            return Err(Error::Config(format!("typo rate {}", self.typo_rate)));
+        }
+
+        Ok(())
+    }
+
+    // Every keystroke for `text`, typos and corrections included. Nothing is
+    // returned if any character can't be typed. Draws from the profile's
+    // generator, so consecutive calls don't repeat the same rhythm.
+    pub fn keystrokes(
+        &mut self,
+        text: &str,
+        encoding: impl Into<Encoding>,
+    ) -> Result<Vec<Keystroke>> {
+        self.validate()?;
+
+        let encoding = encoding.into();
+        key::try_create_message_with(text, encoding)?;
+
+        let interval = self.interval()?;
+        let mut keystrokes = vec![];
+
+        for char in text.chars() {
+            let packet = key::try_create_message_with(char.encode_utf8(&mut [0; 4]), encoding)?;
+            if packet.is_empty() {
+                continue;
+            }
+
+            if self.typo_rate > 0.0 && self.rng.next_f64() < self.typo_rate {
+                if let Some(typo) = self.typo(char, encoding) {
+                    // Noticing the slip takes a moment longer than a keystroke
+                    keystrokes.push(Keystroke {
+                        packet: typo,
+                        delay: self.gap(interval).saturating_mul(2),
+                    });
+                    keystrokes.push(Keystroke {
+                        packet: key::create_command(vec![KeyCode::BackSpace]),
+                        delay: self.gap(interval),
+                    });
+                }
+            }
+
+            let mut delay = self.gap(interval);
+            if char == '\n' {
+                delay = delay.saturating_add(self.newline_pause);
+            } else if PUNCTUATION.contains(&char) {
+                delay = delay.saturating_add(self.punctuation_pause);
+            }
+
+            keystrokes.push(Keystroke { packet, delay });
+        }
+
+        Ok(keystrokes)
+    }
+
+    // Average time between keystrokes
+    fn interval(&self) -> Result<Duration> {
+        let seconds = 60.0 / (self.wpm * CHARS_PER_WORD);
+        match Duration::try_from_secs_f64(seconds) {
+            Ok(interval) if self.wpm.is_finite() && self.wpm > 0.0 => Ok(interval),
+            _ => Err(Error::Config(format!("typing speed {} wpm", self.wpm))),
+        }
+    }
+
+    fn gap(&mut self, interval: Duration) -> Duration {
+        let factor = match self.jitter {
+            Jitter::None => 1.0,
+            Jitter::Uniform(spread) => 1.0 + spread * (2.0 * self.rng.next_f64() - 1.0),
+            Jitter::Normal(deviation) => 1.0 + deviation * self.rng.normal(),
+        };
+
+        let seconds = interval.as_secs_f64() * factor.clamp(MIN_FACTOR, MAX_FACTOR);
+        Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+    }
+
+    // A neighbouring letter key with the same modifiers, for single letters
+    fn typo(&mut self, char: char, encoding: Encoding) -> Option<Vec<u8>> {
+        let [stroke] = encoding.layout.strokes(char)? else {
+            return None;
+        };
+
+        let row = ROWS.iter().find(|row| row.contains(&stroke.key))?;
+        let index = row.iter().position(|key| *key == stroke.key)?;
+        let neighbours: Vec<KeyCode> = [index.checked_sub(1), Some(index + 1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|index| row.get(index).copied())
+            .collect();
+        let key = neighbours[(self.rng.next_u64() % neighbours.len() as u64) as usize];
+
+        let typo = Stroke { key, ..*stroke };
+        Some(key::create_command(typo.keys()))
+    }
+}
+
+// SplitMix64, plenty for timing noise and cheap to seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Standard normal through the Box-Muller transform
+    fn normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+
+        (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::{key::create_command, HostLayout};
+
+    const US: HostLayout = HostLayout::UsQwerty;
+
+    // 60 wpm is 200ms per character
+    fn steady() -> TypingProfile {
+        TypingProfile::new(60.0).jitter(Jitter::None)
+    }
+
+    fn delays(keystrokes: &[Keystroke]) -> Vec<Duration> {
+        keystrokes.iter().map(|keystroke| keystroke.delay).collect()
+    }
+
+    #[test]
+    fn same_seed_same_rhythm() {
+        let text = "The quick brown fox, again.\n";
+        let (mut first, mut again, mut other) = (
+            TypingProfile::new(80.0).typos(0.2).seed(42),
+            TypingProfile::new(80.0).typos(0.2).seed(42),
+            TypingProfile::new(80.0).typos(0.2).seed(43),
+        );
+
+        let first = first.keystrokes(text, US).unwrap();
+        assert_eq!(first, again.keystrokes(text, US).unwrap());
+        assert_ne!(first, other.keystrokes(text, US).unwrap());
+    }
+
+    #[test]
+    fn consecutive_messages_continue_the_sequence() {
+        let mut profile = TypingProfile::new(80.0).seed(7);
+
+        let first = profile.keystrokes("abcdef", US).unwrap();
+        let second = profile.keystrokes("abcdef", US).unwrap();
+        assert_ne!(delays(&first), delays(&second));
+    }
+
+    #[test]
+    fn pauses_after_punctuation_and_newlines() {
+        let mut profile = steady()
+            .punctuation_pause(Duration::from_millis(300))
+            .newline_pause(Duration::from_millis(700));
+        let keystrokes = profile.keystrokes("a.b\nc", US).unwrap();
+
+        assert_eq!(
+            delays(&keystrokes),
+            [200, 500, 200, 900, 200].map(Duration::from_millis)
+        );
+        assert_eq!(keystrokes[1].packet, create_command(vec![KeyCode::Period]));
+    }
+
+    #[test]
+    fn jitter_stays_within_its_spread() {
+        let mut profile = TypingProfile::new(60.0)
+            .jitter(Jitter::Uniform(0.5))
+            .seed(1);
+
+        for delay in delays(&profile.keystrokes("abcdefghijklmnop", US).unwrap()) {
+            assert!(delay >= Duration::from_millis(100), "{delay:?}");
+            assert!(delay <= Duration::from_millis(300), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn typos_hit_a_neighbour_and_are_corrected() {
+        let mut profile = steady().typos(1.0).seed(3);
+        let keystrokes = profile.keystrokes("s", US).unwrap();
+        let packets: Vec<_> = keystrokes.iter().map(|k| k.packet.clone()).collect();
+
+        let neighbours = [KeyCode::A, KeyCode::D].map(|key| create_command(vec![key]));
+        assert!(neighbours.contains(&packets[0]), "{packets:?}");
+        assert_eq!(packets[1], create_command(vec![KeyCode::BackSpace]));
+        assert_eq!(packets[2], create_command(vec![KeyCode::S]));
+        assert_eq!(delays(&keystrokes)[0], Duration::from_millis(400));
+    }
+
+    #[test]
+    fn typos_keep_shift() {
+        let mut profile = steady().typos(1.0);
+        let keystrokes = profile.keystrokes("Q", US).unwrap();
+
+        assert_eq!(
+            keystrokes[0].packet,
+            create_command(vec![KeyCode::Shift, KeyCode::W])
+        );
+    }
+
+    #[test]
+    fn no_typos_outside_the_letter_rows() {
+        let mut profile = steady().typos(1.0);
+
+        assert_eq!(profile.keystrokes("1 .", US).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn untypeable_text_sends_nothing() {
+        let mut profile = steady();
+
+        assert!(matches!(
+            profile.keystrokes("ok\u{1F600}", US),
+            Err(Error::UnsupportedChars(_))
+        ));
+    }
+
+    #[test]
+    fn out_of_range_settings_are_errors() {
+        for wpm in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+            assert!(
+                matches!(
+                    TypingProfile::new(wpm).keystrokes("a", US),
+                    Err(Error::Config(_))
+                ),
+                "{wpm}"
+            );
+        }
+        assert!(TypingProfile::new(60.0).typos(1.5).validate().is_err());
+        assert!(TypingProfile::new(60.0)
+            .jitter(Jitter::Normal(-0.1))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn slow_profiles_saturate_instead_of_panicking() {
+        let mut profile = TypingProfile::new(1e-15)
+            .jitter(Jitter::None)
+            .punctuation_pause(Duration::MAX);
+
+        let keystrokes = profile.keystrokes(".", US).unwrap();
+        assert_eq!(keystrokes[0].delay, Duration::MAX);
+    }
+}
@@ -0,0 +1,212 @@
+// Fallbacks for characters the host layout has no key for, typed through an
+// input method on the target by the character's code point
+
+use super::{
+    key::{create_command, press, release},
+    HostLayout, KeyCode,
+};
+use num_enum::TryFromPrimitive;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, TryFromPrimitive)]
+pub enum UnicodeInput {
+    #[default]
+    Disabled,
+    // Alt held while typing the decimal code point on the keypad, needs Num
+    // Lock on. Rich text controls take any code point, plain ones only the
+    // Windows-1252 range. U+0080 to U+009F can't be typed this way.
+    WindowsAltCode,
+    // Ctrl+Shift+U, the hex code point, then Space, as GTK and IBus expect
+    LinuxHex,
+    // Option held while typing UTF-16 code units in hex, with the Unicode
+    // Hex Input source selected
+    MacHex,
+}
+
+const KEYPAD: [KeyCode; 10] = [
+    KeyCode::KeypadZero,
+    KeyCode::KeypadOne,
+    KeyCode::KeypadTwo,
+    KeyCode::KeypadThree,
+    KeyCode::KeypadFour,
+    KeyCode::KeypadFive,
+    KeyCode::KeypadSix,
+    KeyCode::KeypadSeven,
+    KeyCode::KeypadEight,
+    KeyCode::KeypadNine,
+];
+
+impl UnicodeInput {
+    // None when disabled or `layout` can't type what the method needs
+    pub fn packet(&self, char: char, layout: HostLayout) -> Option<Vec<u8>> {
+        match self {
+            Self::Disabled => None,
+            Self::WindowsAltCode => windows_alt_code(char),
+            Self::LinuxHex => linux_hex(char, layout),
+            Self::MacHex => mac_hex(char),
+        }
+    }
+}
+
+fn windows_alt_code(char: char) -> Option<Vec<u8>> {
+    // A leading zero picks the ANSI code page over the OEM one. Windows-1252
+    // puts other characters at 128 to 159, and without the zero the OEM code
+    // page is used, so the C1 controls have no code.
+    let digits = match char as u32 {
+        0x80..=0x9F => return None,
+        code @ 0..=255 => format!("0{code}"),
+        code => code.to_string(),
+    };
+
+    let mut packet = vec![press(&KeyCode::Alt)];
+    for digit in digits.bytes() {
+        packet.extend(create_command(vec![KEYPAD[(digit - b'0') as usize]]));
+    }
+    packet.push(release(&KeyCode::Alt));
+
+    Some(packet)
+}
+
+fn linux_hex(char: char, layout: HostLayout) -> Option<Vec<u8>> {
+    let u = layout.strokes('u')?.first()?.key;
+
+    let mut packet = create_command(vec![KeyCode::Control, KeyCode::Shift, u]);
+    packet.extend(type_hex(&format!("{:x}", char as u32), layout)?);
+    packet.extend(create_command(vec![KeyCode::Space]));
+
+    Some(packet)
+}
+
+fn mac_hex(char: char) -> Option<Vec<u8>> {
+    let mut units = [0; 2];
+
+    let mut packet = vec![press(&KeyCode::Alt)];
+    for unit in char.encode_utf16(&mut units) {
+        packet.extend(type_hex(&format!("{unit:04x}"), HostLayout::UsQwerty)?);
+    }
+    packet.push(release(&KeyCode::Alt));
+
+    Some(packet)
+}
+
+fn type_hex(hex: &str, layout: HostLayout) -> Option<Vec<u8>> {
+    let mut packet = vec![];
+    for digit in hex.chars() {
+        for stroke in layout.strokes(digit)? {
+            packet.extend(create_command(stroke.keys()));
+        }
+    }
+
+    Some(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tap(key: KeyCode) -> [u8; 2] {
+        [press(&key), release(&key)]
+    }
+
+    fn taps(keys: &[KeyCode]) -> Vec<u8> {
+        keys.iter().flat_map(|key| tap(*key)).collect()
+    }
+
+    fn alt_held(inner: Vec<u8>) -> Vec<u8> {
+        [
+            vec![press(&KeyCode::Alt)],
+            inner,
+            vec![release(&KeyCode::Alt)],
+        ]
+        .concat()
+    }
+
+    fn keypad(digits: &str) -> Vec<u8> {
+        let keys: Vec<KeyCode> = digits
+            .bytes()
+            .map(|digit| KEYPAD[(digit - b'0') as usize])
+            .collect();
+
+        taps(&keys)
+    }
+
+    fn linux(hex: &[KeyCode]) -> Vec<u8> {
+        [
+            create_command(vec![KeyCode::Control, KeyCode::Shift, KeyCode::U]),
+            taps(hex),
+            tap(KeyCode::Space).to_vec(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn disabled_types_nothing() {
+        assert_eq!(
+            UnicodeInput::Disabled.packet('é', HostLayout::UsQwerty),
+            None
+        );
+    }
+
+    #[test]
+    fn windows_alt_codes() {
+        let packet = |char| UnicodeInput::WindowsAltCode.packet(char, HostLayout::UsQwerty);
+
+        assert_eq!(packet('é'), Some(alt_held(keypad("0233"))));
+        assert_eq!(packet('😀'), Some(alt_held(keypad("128512"))));
+        assert_eq!(packet('€'), Some(alt_held(keypad("8364"))));
+    }
+
+    #[test]
+    fn windows_alt_codes_skip_c1_controls() {
+        for char in ['\u{80}', '\u{9F}'] {
+            assert_eq!(
+                UnicodeInput::WindowsAltCode.packet(char, HostLayout::UsQwerty),
+                None
+            );
+        }
+        assert!(UnicodeInput::WindowsAltCode
+            .packet('\u{A0}', HostLayout::UsQwerty)
+            .is_some());
+    }
+
+    #[test]
+    fn linux_hex() {
+        use KeyCode::*;
+        let packet = |char| UnicodeInput::LinuxHex.packet(char, HostLayout::UsQwerty);
+
+        assert_eq!(packet('é'), Some(linux(&[E, Nine])));
+        assert_eq!(packet('😀'), Some(linux(&[One, F, Six, Zero, Zero])));
+    }
+
+    #[test]
+    fn linux_hex_follows_the_layout() {
+        // U is where it always is, the digits are shifted on French
+        let packet = UnicodeInput::LinuxHex
+            .packet('é', HostLayout::French)
+            .unwrap();
+
+        assert_eq!(
+            packet,
+            [
+                create_command(vec![KeyCode::Control, KeyCode::Shift, KeyCode::U]),
+                taps(&[KeyCode::E]),
+                create_command(vec![KeyCode::Shift, KeyCode::Nine]),
+                tap(KeyCode::Space).to_vec(),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn mac_hex() {
+        use KeyCode::*;
+        let packet = |char| UnicodeInput::MacHex.packet(char, HostLayout::German);
+
+        assert_eq!(packet('é'), Some(alt_held(taps(&[Zero, Zero, E, Nine]))));
+        // Surrogate pair d83d de00
+        assert_eq!(
+            packet('😀'),
+            Some(alt_held(taps(&[D, Eight, Three, D, D, E, Zero, Zero])))
+        );
+    }
+}
@@ -0,0 +1,91 @@
+// Serves a locally attached Hagstrom KM device over TCP
+//
+// usage: hagstrom-bridge (--port <port> | --serial-number <serial>) [--listen <address>]
+//                        [--token <token>] [--idle-release <seconds|off>]
+//
+// The token can also be given through HAGSTROM_TOKEN. Held keys are released
+// when a client disconnects. --idle-release also releases them after that
+// many seconds without input, which covers clients that vanish without
+// disconnecting but cuts short any hold longer than the timeout.
+
+use hagstrom_core::{bridge, bridge::Server, Emulator};
+use std::{process, time::Duration};
+
+const USAGE: &str = "usage: hagstrom-bridge (--port <port> | --serial-number <serial>) [--listen <address>] [--token <token>] [--idle-release <seconds|off>]";
+
+fn main() {
+    let mut port = None;
+    let mut serial_number = None;
+    let mut listen = bridge::DEFAULT_ADDRESS.to_owned();
+    let mut token = std::env::var("HAGSTROM_TOKEN").ok();
+    let mut idle_release = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        match (arg.as_str(), value) {
+            ("--port", Some(value)) => port = Some(value),
+            ("--serial-number", Some(value)) => serial_number = Some(value),
+            ("--listen", Some(value)) => listen = value,
+            ("--token", Some(value)) => token = Some(value),
+            ("--idle-release", Some(value)) => match parse_idle_release(&value) {
+                Some(value) => idle_release = value,
+                None => {
+                    eprintln!("{USAGE}");
+                    process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("{USAGE}");
+                process::exit(2);
+            }
+        }
+    }
+
+    let emulator = match (port, serial_number) {
+        (Some(port), None) => Emulator::new(&port),
+        (None, Some(serial_number)) => Emulator::open_serial_number(&serial_number),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    let emulator = match emulator {
+        Ok(emulator) => emulator,
+        Err(err) => {
+            eprintln!("Failed to open device: {err}");
+            process::exit(1);
+        }
+    };
+
+    let mut server = match Server::bind(&listen, emulator) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Failed to listen on {listen}: {err}");
+            process::exit(1);
+        }
+    };
+    if let Some(token) = token {
+        server = server.token(token);
+    }
+    server = server.idle_release(idle_release);
+
+    println!("Serving on {listen}");
+    if let Err(err) = server.serve() {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
+
+// `off` disables it, anything else has to be a positive number of seconds
+fn parse_idle_release(value: &str) -> Option<Option<Duration>> {
+    if value == "off" {
+        return Some(None);
+    }
+
+    let seconds: f64 = value.parse().ok()?;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(after) if !after.is_zero() => Some(Some(after)),
+        _ => None,
+    }
+}
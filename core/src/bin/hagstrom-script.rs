@@ -0,0 +1,121 @@
+// Runs a macro script, see `hagstrom_core::script` for the format
+//
+// usage: hagstrom-script <script> (--port <port> | --serial-number <serial> |
+//                        --connect <address> | --dry-run) [--token <token>]
+//                        [--section <name>] [--set <name>=<value>]...
+//                        [--layout <layout>] [--journal <path>]
+//
+// The token can also be given through HAGSTROM_TOKEN
+
+use hagstrom_core::{action::HostLayout, error::Error, script::Script, signal, Emulator};
+use std::process;
+
+const USAGE: &str = "usage: hagstrom-script <script> (--port <port> | --serial-number <serial> | --connect <address> | --dry-run) [--token <token>] [--section <name>] [--set <name>=<value>]... [--layout <layout>] [--journal <path>]";
+
+enum Target {
+    Port(String),
+    SerialNumber(String),
+    Connect(String),
+    DryRun,
+}
+
+fn main() {
+    let mut path = None;
+    let mut target = None;
+    let mut token = std::env::var("HAGSTROM_TOKEN").ok();
+    let mut section = None;
+    let mut variables = vec![];
+    let mut layout = None;
+    let mut journal = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--dry-run" {
+            target = Some(Target::DryRun);
+            continue;
+        }
+        if !arg.starts_with("--") && path.is_none() {
+            path = Some(arg);
+            continue;
+        }
+
+        let value = args.next();
+        match (arg.as_str(), value) {
+            ("--port", Some(value)) => target = Some(Target::Port(value)),
+            ("--serial-number", Some(value)) => target = Some(Target::SerialNumber(value)),
+            ("--connect", Some(value)) => target = Some(Target::Connect(value)),
+            ("--token", Some(value)) => token = Some(value),
+            ("--section", Some(value)) => section = Some(value),
+            ("--set", Some(value)) => match value.split_once('=') {
+                Some((name, value)) => variables.push((name.to_owned(), value.to_owned())),
+                None => usage(),
+            },
+            ("--layout", Some(value)) => match value.parse::<HostLayout>() {
+                Ok(value) => layout = Some(value),
+                Err(_) => usage(),
+            },
+            ("--journal", Some(value)) => journal = Some(value),
+            _ => usage(),
+        }
+    }
+
+    let (Some(path), Some(target)) = (path, target) else {
+        usage();
+    };
+
+    let script = match Script::open(&path) {
+        Ok(script) => variables
+            .into_iter()
+            .fold(script, |script, (name, value)| script.set(name, value)),
+        Err(err @ Error::Script { .. }) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Failed to load {path}: {err}");
+            process::exit(1);
+        }
+    };
+
+    let emulator = match target {
+        Target::Port(port) => Emulator::new(&port),
+        Target::SerialNumber(serial_number) => Emulator::open_serial_number(&serial_number),
+        Target::Connect(address) => Emulator::connect(&address, token.as_deref()),
+        Target::DryRun => Ok(Emulator::dry_run()),
+    };
+    let mut emulator = match emulator {
+        Ok(emulator) => emulator,
+        Err(err) => {
+            eprintln!("Failed to open device: {err}");
+            process::exit(1);
+        }
+    };
+    if let Some(layout) = layout {
+        emulator.set_layout(layout);
+    }
+    if let Some(journal) = journal {
+        if let Err(err) = emulator.start_journal(&journal) {
+            eprintln!("Failed to start journal {journal}: {err}");
+            process::exit(1);
+        }
+    }
+    if let Err(err) = signal::install() {
+        eprintln!("Failed to install signal handler: {err}");
+    }
+
+    let result = match &section {
+        Some(section) => script.run_section(section, &mut emulator),
+        None => script.run(&mut emulator),
+    };
+    let closed = emulator.close();
+
+    if let Err(err) = result.and(closed) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(2);
+}
@@ -0,0 +1,73 @@
+// Virtual Hagstrom KM device on a pseudo terminal
+//
+// usage: hagstrom-sim [--buffer <bytes> --drain <bytes per second>] [--link <path>]
+
+#[cfg(unix)]
+fn main() {
+    use hagstrom_core::sim::{BufferLimit, VirtualDevice};
+    use std::{process, thread, time::Instant};
+
+    let mut capacity = None;
+    let mut drain_rate = None;
+    let mut link = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        match (arg.as_str(), value) {
+            ("--buffer", Some(value)) => capacity = value.parse().ok(),
+            ("--drain", Some(value)) => drain_rate = value.parse().ok(),
+            ("--link", Some(value)) => link = Some(value),
+            _ => {
+                eprintln!(
+                    "usage: hagstrom-sim [--buffer <bytes> --drain <bytes per second>] [--link <path>]"
+                );
+                process::exit(2);
+            }
+        }
+    }
+
+    let limit = match (capacity, drain_rate) {
+        (Some(capacity), Some(drain_rate)) => Some(BufferLimit {
+            capacity,
+            drain_rate,
+        }),
+        (None, None) => None,
+        _ => {
+            eprintln!("--buffer and --drain must be given together");
+            process::exit(2);
+        }
+    };
+
+    let start = Instant::now();
+    let listener = Box::new(move |event: &hagstrom_core::sim::Event| {
+        println!("[{:>10.3}] {event}", start.elapsed().as_secs_f64());
+    });
+
+    let device = match VirtualDevice::spawn(limit, Some(listener)) {
+        Ok(device) => device,
+        Err(err) => {
+            eprintln!("Failed to open pty: {err}");
+            process::exit(1);
+        }
+    };
+
+    if let Some(link) = link {
+        let _ = std::fs::remove_file(&link);
+        if let Err(err) = std::os::unix::fs::symlink(device.path(), &link) {
+            eprintln!("Failed to link {link}: {err}");
+            process::exit(1);
+        }
+    }
+
+    println!("Listening on {}", device.path());
+    loop {
+        thread::park();
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("hagstrom-sim requires a unix pseudo terminal");
+    std::process::exit(1);
+}
@@ -0,0 +1,82 @@
+// Drives an emulator on another machine over TCP
+//
+// Every frame is `[kind: u8][length: u32 BE][payload]`. A client opens with
+// Hello carrying the shared token, then gets exactly one reply per frame.
+
+mod server;
+
+pub use server::Server;
+
+use crate::error::{Error, Result};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::io::{Read, Write};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+// Anything bigger than this is a broken or hostile peer
+pub const MAX_PAYLOAD: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum FrameKind {
+    // Client to server
+    Hello = 0x01,
+    Data = 0x02,
+    Release = 0x03,
+
+    // Server to client
+    Ok = 0x80,
+    Error = 0x81,
+    Busy = 0x82,
+    Unauthorized = 0x83,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: FrameKind, payload: Vec<u8>) -> Self {
+        Self { kind, payload }
+    }
+
+    pub fn empty(kind: FrameKind) -> Self {
+        Self::new(kind, vec![])
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+
+        let kind = FrameKind::try_from(header[0])
+            .map_err(|_| Error::Protocol(format!("unknown frame kind 0x{:02x}", header[0])))?;
+        let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if length > MAX_PAYLOAD {
+            return Err(Error::Protocol(format!("{length} byte payload")));
+        }
+
+        let mut payload = vec![0; length];
+        reader.read_exact(&mut payload)?;
+
+        Ok(Self { kind, payload })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if self.payload.len() > MAX_PAYLOAD {
+            return Err(Error::Protocol(format!(
+                "{} byte payload",
+                self.payload.len()
+            )));
+        }
+
+        let mut bytes = Vec::with_capacity(5 + self.payload.len());
+        bytes.push(self.kind.into());
+        bytes.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        writer.write_all(&bytes)?;
+
+        Ok(())
+    }
+}
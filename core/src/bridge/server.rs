@@ -0,0 +1,174 @@
+use super::{Frame, FrameKind};
+use crate::{
+    error::{Error, Result},
+    Emulator,
+};
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Owns a local emulator and serves it to one writer at a time. A second
+// client is turned away with Busy until the first disconnects.
+pub struct Server {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    emulator: Mutex<Emulator>,
+    token: Option<String>,
+    idle_release: Option<Duration>,
+    busy: AtomicBool,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(address: A, emulator: Emulator) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            shared: Arc::new(Shared {
+                emulator: Mutex::new(emulator),
+                token: None,
+                idle_release: None,
+                busy: AtomicBool::new(false),
+            }),
+        })
+    }
+
+    // Clients must present this in their Hello, anyone may connect otherwise
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.shared_mut().token = Some(token.into());
+        self
+    }
+
+    // Releases held keys and buttons once the writer has been quiet this long,
+    // for clients that vanish without closing their connection. Off by
+    // default, since a client holding keys on purpose for longer than this
+    // has them released under it while its own emulator still counts them as
+    // held. Keys are always released when the client disconnects.
+    pub fn idle_release(mut self, after: Option<Duration>) -> Self {
+        self.shared_mut().idle_release = after.filter(|after| !after.is_zero());
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    // Accepts clients until the listener fails, each on its own thread
+    pub fn serve(self) -> Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let shared = self.shared.clone();
+
+            thread::spawn(move || {
+                let peer = stream
+                    .peer_addr()
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_default();
+                if let Err(err) = shared.handle(stream) {
+                    eprintln!("Client {peer}: {err}");
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn shared_mut(&mut self) -> &mut Shared {
+        Arc::get_mut(&mut self.shared).expect("server is configured before serving")
+    }
+}
+
+impl Shared {
+    fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+        let hello = Frame::read_from(&mut stream)?;
+        if hello.kind != FrameKind::Hello || !self.authorize(&hello.payload) {
+            return Frame::empty(FrameKind::Unauthorized).write_to(&mut stream);
+        }
+        if self.busy.swap(true, Ordering::SeqCst) {
+            return Frame::empty(FrameKind::Busy).write_to(&mut stream);
+        }
+
+        let result = self.session(&mut stream);
+
+        // Don't leave keys down on the target when a client goes away
+        let released = self.release();
+        self.busy.store(false, Ordering::SeqCst);
+
+        result.and(released)
+    }
+
+    fn session(&self, stream: &mut TcpStream) -> Result<()> {
+        Frame::empty(FrameKind::Ok).write_to(stream)?;
+        stream.set_read_timeout(self.idle_release)?;
+
+        loop {
+            match stream.peek(&mut [0]) {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    self.release()?;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            }
+
+            let frame = Frame::read_from(stream)?;
+            let result = match frame.kind {
+                FrameKind::Data => self.emulator()?.write(frame.payload, Duration::ZERO),
+                FrameKind::Release => self.emulator()?.release_all(),
+                kind => Err(Error::Protocol(format!("unexpected {kind:?} frame"))),
+            };
+
+            match result {
+                Ok(_) => Frame::empty(FrameKind::Ok),
+                Err(err) => Frame::new(FrameKind::Error, err.to_string().into_bytes()),
+            }
+            .write_to(stream)?;
+        }
+    }
+
+    fn release(&self) -> Result<()> {
+        let mut emulator = self.emulator()?;
+        if emulator.held_keys().is_empty() && emulator.held_buttons().is_empty() {
+            return Ok(());
+        }
+
+        emulator.release_all()
+    }
+
+    fn emulator(&self) -> Result<std::sync::MutexGuard<'_, Emulator>> {
+        Ok(self.emulator.lock()?)
+    }
+
+    // Compares in constant time so the token can't be guessed byte by byte
+    fn authorize(&self, token: &[u8]) -> bool {
+        let Some(expected) = &self.token else {
+            return true;
+        };
+
+        expected.len() == token.len()
+            && expected
+                .bytes()
+                .zip(token)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
@@ -0,0 +1,239 @@
+// Finds serial ports that look like Hagstrom KM emulators from their USB
+// descriptors. Anything reporting Hagstrom as its manufacturer or product is a
+// confirmed match. The USB models are built on FTDI bridges, so other FTDI
+// ports are reported as possible matches, since any FTDI adapter looks the
+// same.
+
+use crate::error::Result;
+use serialport::SerialPortType;
+
+pub const FTDI_VID: u16 = 0x0403;
+pub const FTDI_PIDS: [u16; 4] = [0x6001, 0x6010, 0x6014, 0x6015];
+
+// Ordered from weakest to strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Possible,
+    Confirmed,
+}
+
+impl Confidence {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Possible => "possible",
+            Self::Confirmed => "confirmed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub port_name: String,
+    pub vid: u16,
+    pub pid: u16,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+}
+
+impl DeviceInfo {
+    // None when nothing suggests a Hagstrom device
+    pub fn confidence(&self) -> Option<Confidence> {
+        let named = [&self.manufacturer, &self.product]
+            .into_iter()
+            .flatten()
+            .any(|name| name.to_lowercase().contains("hagstrom"));
+
+        if named {
+            Some(Confidence::Confirmed)
+        } else if self.vid == FTDI_VID && FTDI_PIDS.contains(&self.pid) {
+            Some(Confidence::Possible)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_hagstrom(&self) -> bool {
+        self.confidence() == Some(Confidence::Confirmed)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    vid: Option<u16>,
+    pid: Option<u16>,
+    manufacturer: Option<String>,
+    serial_number: Option<String>,
+    confirmed_only: bool,
+    any_device: bool,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vid(mut self, vid: u16) -> Self {
+        self.vid = Some(vid);
+        self
+    }
+
+    pub fn pid(mut self, pid: u16) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    // Case-insensitive substring match
+    pub fn manufacturer(mut self, manufacturer: &str) -> Self {
+        self.manufacturer = Some(manufacturer.to_lowercase());
+        self
+    }
+
+    pub fn serial_number(mut self, serial_number: &str) -> Self {
+        self.serial_number = Some(serial_number.to_owned());
+        self
+    }
+
+    // Leave out possible matches, plain FTDI ports without Hagstrom in their
+    // descriptors
+    pub fn confirmed_only(mut self) -> Self {
+        self.confirmed_only = true;
+        self
+    }
+
+    // Also match USB serial ports that don't look like a Hagstrom device
+    pub fn any_device(mut self) -> Self {
+        self.any_device = true;
+        self
+    }
+
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        let minimum = match self.confirmed_only {
+            true => Confidence::Confirmed,
+            false => Confidence::Possible,
+        };
+
+        (self.any_device || device.confidence() >= Some(minimum))
+            && self.vid.is_none_or(|vid| device.vid == vid)
+            && self.pid.is_none_or(|pid| device.pid == pid)
+            && self
+                .serial_number
+                .as_ref()
+                .is_none_or(|serial| device.serial_number.as_ref() == Some(serial))
+            && self.manufacturer.as_ref().is_none_or(|manufacturer| {
+                device
+                    .manufacturer
+                    .as_ref()
+                    .is_some_and(|name| name.to_lowercase().contains(manufacturer))
+            })
+    }
+}
+
+pub fn discover() -> Result<Vec<DeviceInfo>> {
+    discover_with(&Filter::default())
+}
+
+// Confirmed matches first
+pub fn discover_with(filter: &Filter) -> Result<Vec<DeviceInfo>> {
+    let mut devices: Vec<DeviceInfo> = usb_ports()?
+        .into_iter()
+        .filter(|device| filter.matches(device))
+        .collect();
+    devices.sort_by_key(|device| std::cmp::Reverse(device.confidence()));
+
+    Ok(devices)
+}
+
+pub fn find_serial_number(serial_number: &str) -> Result<Option<DeviceInfo>> {
+    Ok(usb_ports()?
+        .into_iter()
+        .find(|device| device.serial_number.as_deref() == Some(serial_number)))
+}
+
+pub fn find_port(port_name: &str) -> Result<Option<DeviceInfo>> {
+    Ok(usb_ports()?
+        .into_iter()
+        .find(|device| device.port_name == port_name))
+}
+
+fn usb_ports() -> Result<Vec<DeviceInfo>> {
+    Ok(serialport::available_ports()?
+        .into_iter()
+        .filter_map(|port| match port.port_type {
+            SerialPortType::UsbPort(info) => Some(DeviceInfo {
+                port_name: port.port_name,
+                vid: info.vid,
+                pid: info.pid,
+                serial_number: info.serial_number,
+                manufacturer: info.manufacturer,
+                product: info.product,
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(vid: u16, pid: u16, manufacturer: &str, product: &str) -> DeviceInfo {
+        DeviceInfo {
+            port_name: "/dev/ttyUSB0".to_owned(),
+            vid,
+            pid,
+            serial_number: Some("A1".to_owned()),
+            manufacturer: Some(manufacturer.to_owned()),
+            product: Some(product.to_owned()),
+        }
+    }
+
+    #[test]
+    fn hagstrom_descriptors_are_confirmed() {
+        let devices = [
+            device(FTDI_VID, 0x6001, "Hagstrom Electronics", "KM232-USB"),
+            device(FTDI_VID, 0x6001, "FTDI", "HAGSTROM KM-USB"),
+            device(0x1234, 0x5678, "hagstrom", ""),
+        ];
+
+        for device in devices {
+            assert_eq!(
+                device.confidence(),
+                Some(Confidence::Confirmed),
+                "{device:?}"
+            );
+            assert!(device.is_hagstrom());
+        }
+    }
+
+    #[test]
+    fn plain_ftdi_ports_are_possible() {
+        let device = device(FTDI_VID, 0x6001, "FTDI", "FT232R USB UART");
+
+        assert_eq!(device.confidence(), Some(Confidence::Possible));
+        assert!(!device.is_hagstrom());
+        assert!(Filter::new().matches(&device));
+        assert!(!Filter::new().confirmed_only().matches(&device));
+    }
+
+    #[test]
+    fn other_ports_are_not_matched() {
+        let device = device(0x10C4, 0xEA60, "Silicon Labs", "CP2102 USB to UART");
+
+        assert_eq!(device.confidence(), None);
+        assert!(!Filter::new().matches(&device));
+        assert!(Filter::new().any_device().matches(&device));
+    }
+
+    #[test]
+    fn filter_fields() {
+        let device = device(FTDI_VID, 0x6015, "Hagstrom Electronics", "KM-USB");
+
+        assert!(Filter::new()
+            .pid(0x6015)
+            .manufacturer("hagstrom")
+            .matches(&device));
+        assert!(!Filter::new().pid(0x6001).matches(&device));
+        assert!(!Filter::new().serial_number("B2").matches(&device));
+    }
+}
@@ -0,0 +1,275 @@
+use super::{Config, Emulator, ReconnectEvent, ReconnectListener, ReconnectPolicy};
+use crate::{
+    action::{
+        key::{Encoding, UnsupportedPolicy},
+        HostLayout, TypingProfile, UnicodeInput,
+    },
+    discovery,
+    error::{Error, Result},
+    journal,
+    pacing::Pacing,
+    transport::{DataBits, FlowControl, Parity, Serial, StopBits, Tcp, Transport},
+};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+const MAX_CHUNK_SIZE: usize = 4096;
+
+#[derive(Clone)]
+pub struct EmulatorBuilder {
+    baud_rate: u32,
+    timeout: Duration,
+    flow_control: FlowControl,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+    chunk_size: usize,
+    buffer_size: usize,
+    report_rate: u32,
+    reconnect: Option<ReconnectPolicy>,
+    encoding: Encoding,
+    typing: Option<TypingProfile>,
+    listener: Option<ReconnectListener>,
+    journal: Option<PathBuf>,
+}
+
+impl Default for EmulatorBuilder {
+    fn default() -> Self {
+        Self {
+            baud_rate: 19_200,
+            timeout: Duration::from_millis(10),
+            flow_control: FlowControl::None,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            chunk_size: 16,
+            buffer_size: Pacing::default().buffer_size,
+            report_rate: Pacing::default().report_rate,
+            reconnect: None,
+            encoding: Encoding::default(),
+            typing: None,
+            listener: None,
+            journal: None,
+        }
+    }
+}
+
+impl EmulatorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    // Largest packet written in one go, anything longer is split into chunks
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    // Size of the device's input buffer, at most this many bytes are in
+    // flight before writes wait for it to drain
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    // USB HID reports the device emits per second, each key byte or mouse
+    // frame takes one report
+    pub fn report_rate(mut self, report_rate: u32) -> Self {
+        self.report_rate = report_rate;
+        self
+    }
+
+    // Keyboard layout the target OS is set to, used to type messages
+    pub fn layout(mut self, layout: HostLayout) -> Self {
+        self.encoding.layout = layout;
+        self
+    }
+
+    // How to type characters the layout has no key for
+    pub fn unicode_input(mut self, unicode: UnicodeInput) -> Self {
+        self.encoding.unicode = unicode;
+        self
+    }
+
+    // What to do with characters that can't be typed at all
+    pub fn unsupported(mut self, policy: UnsupportedPolicy) -> Self {
+        self.encoding.unsupported = policy;
+        self
+    }
+
+    // Type messages with human-like timing instead of in one burst
+    pub fn typing(mut self, profile: TypingProfile) -> Self {
+        self.typing = Some(profile);
+        self
+    }
+
+    // Reopen the port and retry from the last action boundary when a write
+    // fails, disabled by default
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    // Called for every reconnect event, they're logged to stderr otherwise
+    pub fn on_reconnect<F>(mut self, listener: F) -> Self
+    where
+        F: Fn(&ReconnectEvent) + Send + Sync + 'static,
+    {
+        self.listener = Some(Arc::new(listener));
+        self
+    }
+
+    // Records every chunk sent to the device, see `journal` for the format
+    pub fn journal(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal = Some(path.into());
+        self
+    }
+
+    pub fn open(self, port_id: &str) -> Result<Emulator> {
+        self.validate()?;
+
+        let port = serialport::new(port_id, self.baud_rate)
+            .timeout(self.timeout)
+            .flow_control(self.flow_control)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits);
+
+        self.build(Serial::open_with(port)?)
+    }
+
+    // Port names shuffle between boots, USB serial numbers don't
+    pub fn open_serial_number(self, serial_number: &str) -> Result<Emulator> {
+        match discovery::find_serial_number(serial_number)? {
+            Some(device) => self.open(&device.port_name),
+            None => Err(Error::DeviceNotFound(serial_number.to_owned())),
+        }
+    }
+
+    pub fn connect(self, address: &str, token: Option<&str>) -> Result<Emulator> {
+        self.validate()?;
+        self.build(Tcp::connect(address, token)?)
+    }
+
+    pub fn build<T: Transport + 'static>(self, transport: T) -> Result<Emulator> {
+        self.validate()?;
+
+        let journal = match &self.journal {
+            Some(path) => Some(journal::Writer::open(path)?),
+            None => None,
+        };
+
+        Ok(Emulator::from_parts(
+            Box::new(transport),
+            self.config(),
+            self.listener,
+            journal,
+        ))
+    }
+
+    pub(super) fn config(&self) -> Config {
+        Config {
+            chunk_size: self.chunk_size,
+            pacing: Pacing {
+                buffer_size: self.buffer_size,
+                report_rate: self.report_rate,
+                line_rate: self.baud_rate / self.frame_bits(),
+            },
+            reconnect: self.reconnect,
+            encoding: self.encoding,
+            typing: self.typing,
+        }
+    }
+
+    // Start bit, data bits, parity and stop bits on the wire per byte
+    fn frame_bits(&self) -> u32 {
+        let data_bits = match self.data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        };
+        let parity_bits = match self.parity {
+            Parity::None => 0,
+            Parity::Odd | Parity::Even => 1,
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+
+        1 + data_bits + parity_bits + stop_bits
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.timeout.is_zero() {
+            return Err(Error::Config("timeout must be non-zero".to_owned()));
+        }
+
+        if !(1..=MAX_CHUNK_SIZE).contains(&self.chunk_size) {
+            return Err(Error::Config(format!(
+                "chunk size must be between 1 and {MAX_CHUNK_SIZE}, got {}",
+                self.chunk_size
+            )));
+        }
+
+        if self.chunk_size > self.buffer_size {
+            return Err(Error::Config(format!(
+                "chunk size {} exceeds the device buffer size {}",
+                self.chunk_size, self.buffer_size
+            )));
+        }
+
+        if matches!(self.reconnect, Some(policy) if policy.max_attempts == 0) {
+            return Err(Error::Config(
+                "reconnect policy needs at least one attempt".to_owned(),
+            ));
+        }
+
+        if let Some(profile) = &self.typing {
+            profile.validate()?;
+        }
+
+        if self.report_rate == 0 {
+            return Err(Error::Config("report rate must be non-zero".to_owned()));
+        }
+
+        if self.baud_rate < self.frame_bits() {
+            return Err(Error::Config(format!(
+                "baud rate {} is too low to send a byte per second",
+                self.baud_rate
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{
+    error::{Error, Result},
+    signal,
+    sim::Simulator,
+    transport::Transport,
+    FLUSH,
+};
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+// The transport together with the device state decoded from what went
+// through it. Shared so the signal handler can reach an emulator that is in
+// the middle of a write on another thread.
+pub(crate) struct Link {
+    transport: Box<dyn Transport>,
+    state: Simulator,
+}
+
+pub(crate) type SharedLink = Arc<Mutex<Link>>;
+
+impl Link {
+    pub(crate) fn new(transport: Box<dyn Transport>) -> SharedLink {
+        Arc::new(Mutex::new(Self {
+            transport,
+            state: Simulator::new(),
+        }))
+    }
+
+    pub(crate) fn state(&self) -> &Simulator {
+        &self.state
+    }
+
+    // Checked under the lock, so once the signal handler has released this
+    // link nothing else can reach the device before the process exits
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        if signal::interrupted() {
+            return Err(Error::Interrupted);
+        }
+
+        self.send(chunk)
+    }
+
+    fn send(&mut self, chunk: &[u8]) -> Result<()> {
+        self.transport.write_all(chunk)?;
+        self.state.feed(chunk);
+
+        Ok(())
+    }
+
+    pub(crate) fn reconnect(&mut self) -> Result<()> {
+        self.transport.reconnect()?;
+        self.state.reset();
+
+        Ok(())
+    }
+
+    // Unpaced, for when there is no time left to wait on the device. Goes
+    // through even after an interrupt, it's what the handler sends.
+    pub(crate) fn release_all(&mut self) -> Result<()> {
+        let packet = self.state.release_packet();
+        self.send(&packet)?;
+        self.send(&[FLUSH])
+    }
+
+    pub(crate) fn finish(&mut self, elapsed: Duration) -> Result<()> {
+        self.transport.finish(elapsed)
+    }
+}
+
+pub(crate) fn lock(link: &SharedLink) -> MutexGuard<'_, Link> {
+    match link.lock() {
+        Ok(link) => link,
+        Err(err) => err.into_inner(),
+    }
+}
@@ -0,0 +1,608 @@
+mod builder;
+pub(crate) mod link;
+mod reconnect;
+
+pub use builder::EmulatorBuilder;
+pub use reconnect::{ReconnectEvent, ReconnectListener, ReconnectPolicy};
+
+use crate::{
+    action::{
+        key::{self, Encoding, UnsupportedPolicy},
+        Chord, HostLayout, KeyCode, MouseAction, MouseButton, Sequence, TypingProfile,
+        UnicodeInput, KEY_MAP,
+    },
+    error::{Error, Result},
+    journal::{self, Kind},
+    pacing::{Pacer, Pacing},
+    signal,
+    transport::{DryRun, Transport},
+    FLUSH,
+};
+use link::{Link, SharedLink};
+use std::{
+    path::Path,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    chunk_size: usize,
+    pacing: Pacing,
+    reconnect: Option<ReconnectPolicy>,
+    encoding: Encoding,
+    typing: Option<TypingProfile>,
+}
+
+pub struct Emulator {
+    link: SharedLink,
+    config: Config,
+    pacer: Pacer,
+    listener: Option<ReconnectListener>,
+    journal: Option<journal::Writer>,
+    // What the bytes currently being written are for, as tagged in the journal
+    action: Kind,
+    closed: bool,
+}
+
+impl Emulator {
+    pub fn new(port_id: &str) -> Result<Self> {
+        EmulatorBuilder::default().open(port_id)
+    }
+
+    pub fn open_serial_number(serial_number: &str) -> Result<Self> {
+        EmulatorBuilder::default().open_serial_number(serial_number)
+    }
+
+    // Drives a device attached to a `hagstrom-bridge` on another machine
+    pub fn connect(address: &str, token: Option<&str>) -> Result<Self> {
+        EmulatorBuilder::default().connect(address, token)
+    }
+
+    // Prints the decoded events to stdout instead of writing to a device,
+    // with the byte count and estimated duration once closed
+    pub fn dry_run() -> Self {
+        Self::with_transport(DryRun::new())
+    }
+
+    pub fn builder() -> EmulatorBuilder {
+        EmulatorBuilder::default()
+    }
+
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> Self {
+        Self::from_parts(
+            Box::new(transport),
+            EmulatorBuilder::default().config(),
+            None,
+            None,
+        )
+    }
+
+    fn from_parts(
+        transport: Box<dyn Transport>,
+        config: Config,
+        listener: Option<ReconnectListener>,
+        journal: Option<journal::Writer>,
+    ) -> Self {
+        let pacer = match transport.is_simulated() {
+            true => Pacer::new_virtual(config.pacing),
+            false => Pacer::new(config.pacing),
+        };
+        let link = Link::new(transport);
+        signal::register(&link);
+
+        Self {
+            link,
+            pacer,
+            config,
+            listener,
+            journal,
+            action: Kind::default(),
+            closed: false,
+        }
+    }
+
+    pub fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.config.reconnect = policy;
+    }
+
+    pub fn layout(&self) -> HostLayout {
+        self.config.encoding.layout
+    }
+
+    pub fn set_layout(&mut self, layout: HostLayout) {
+        self.config.encoding.layout = layout;
+    }
+
+    pub fn unicode_input(&self) -> UnicodeInput {
+        self.config.encoding.unicode
+    }
+
+    pub fn set_unicode_input(&mut self, unicode: UnicodeInput) {
+        self.config.encoding.unicode = unicode;
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.config.encoding
+    }
+
+    pub fn set_unsupported_policy(&mut self, policy: UnsupportedPolicy) {
+        self.config.encoding.unsupported = policy;
+    }
+
+    // Types messages one keystroke at a time with the profile's timing,
+    // `None` sends each message as a single packet
+    pub fn set_typing_profile(&mut self, profile: Option<TypingProfile>) -> Result<()> {
+        if let Some(profile) = &profile {
+            profile.validate()?;
+        }
+        self.config.typing = profile;
+
+        Ok(())
+    }
+
+    pub fn on_reconnect<F>(&mut self, listener: F)
+    where
+        F: Fn(&ReconnectEvent) + Send + Sync + 'static,
+    {
+        self.listener = Some(Arc::new(listener));
+    }
+
+    // Records every chunk written from here on, appended to `path` if it
+    // already holds a journal. Replaces any journal already being written.
+    pub fn start_journal<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.journal = Some(journal::Writer::open(path)?);
+
+        Ok(())
+    }
+
+    pub fn stop_journal(&mut self) {
+        self.journal = None;
+    }
+
+    // Named position in the journal to start or stop a replay at
+    pub fn mark(&mut self, name: &str) {
+        if let Some(journal) = &mut self.journal {
+            if let Err(err) = journal.mark(name) {
+                self.journal_failed(err);
+            }
+        }
+    }
+
+    // A non-zero `sleep_duration` is measured from when the device has
+    // finished processing the packet, not from when it was written
+    pub fn write(&mut self, packet: Vec<u8>, sleep_duration: Duration) -> Result<()> {
+        self.write_as(Kind::Raw, packet, sleep_duration)
+    }
+
+    // Same as `write`, with the packet tagged as `kind` in the journal
+    pub fn write_as(
+        &mut self,
+        kind: Kind,
+        packet: Vec<u8>,
+        sleep_duration: Duration,
+    ) -> Result<()> {
+        let previous = std::mem::replace(&mut self.action, kind);
+        let result = self.write_packet(&packet);
+        self.action = previous;
+
+        result?;
+        self.wait(sleep_duration);
+
+        Ok(())
+    }
+
+    pub fn write_message(&mut self, message: &str, sleep_duration: Duration) -> Result<()> {
+        let encoding = self.config.encoding;
+        let Some(profile) = &mut self.config.typing else {
+            let packet = key::try_create_message_with(message, encoding)?;
+            return self.write_as(Kind::Message, packet, sleep_duration);
+        };
+
+        for keystroke in profile.keystrokes(message, encoding)? {
+            self.write_as(Kind::Message, keystroke.packet, keystroke.delay)?;
+        }
+        self.wait(sleep_duration);
+
+        Ok(())
+    }
+
+    pub fn write_command(&mut self, keys: Vec<KeyCode>, sleep_duration: Duration) -> Result<()> {
+        self.write_as(Kind::Command, key::create_command(keys), sleep_duration)
+    }
+
+    pub fn write_chord(&mut self, chord: &Chord, sleep_duration: Duration) -> Result<()> {
+        self.write_as(Kind::Command, chord.packet(), sleep_duration)
+    }
+
+    // Text mixed with keys, see `Sequence` for the syntax
+    pub fn write_sequence(&mut self, sequence: &str, sleep_duration: Duration) -> Result<()> {
+        let packet = Sequence::parse(sequence)?.packet(self.config.encoding)?;
+        self.write_as(Kind::Command, packet, sleep_duration)
+    }
+
+    pub fn write_mouse(&mut self, action: MouseAction, sleep_duration: Duration) -> Result<()> {
+        self.write_as(Kind::Mouse, action.as_packet(), sleep_duration)
+    }
+
+    // Leaves `key` down across writes until `key_up` or a release. Pressing a
+    // key that is already down does nothing.
+    pub fn key_down(&mut self, key: KeyCode) -> Result<()> {
+        if self.is_held(key) {
+            return Ok(());
+        }
+
+        self.write_as(Kind::Command, vec![key::press(&key)], Duration::ZERO)
+    }
+
+    pub fn key_up(&mut self, key: KeyCode) -> Result<()> {
+        if !self.is_held(key) {
+            return Ok(());
+        }
+
+        self.write_as(Kind::Command, vec![key::release(&key)], Duration::ZERO)
+    }
+
+    // Presses `keys` in order, keeps them down for `duration` once the device
+    // has processed the presses, then releases them in reverse. Keys that
+    // were already down stay down.
+    pub fn hold(&mut self, keys: &[KeyCode], duration: Duration) -> Result<()> {
+        let mut pressed: Vec<KeyCode> = vec![];
+        for key in keys {
+            if !self.is_held(*key) && !pressed.contains(key) {
+                pressed.push(*key);
+            }
+        }
+
+        let presses = pressed.iter().map(key::press).collect();
+        self.write_as(Kind::Command, presses, duration)?;
+
+        let releases = pressed.iter().rev().map(key::release).collect();
+        self.write_as(Kind::Command, releases, Duration::ZERO)
+    }
+
+    pub fn write_byte(&mut self, byte: u8) -> Result<()> {
+        self.write_packet(&[byte])
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.write_as(Kind::Flush, vec![FLUSH], Duration::ZERO)
+    }
+
+    pub fn wait(&mut self, duration: Duration) {
+        if !duration.is_zero() {
+            self.pacer.wait(duration);
+        }
+    }
+
+    // How long the device needs for everything sent so far, an estimate when
+    // nothing is actually being sent
+    pub fn elapsed(&self) -> Duration {
+        self.pacer.elapsed()
+    }
+
+    pub fn held_keys(&self) -> Vec<KeyCode> {
+        let mut keys: Vec<KeyCode> = link::lock(&self.link)
+            .state()
+            .held_keys()
+            .iter()
+            .copied()
+            .collect();
+        keys.sort_by_key(|key| *key as u8);
+
+        keys
+    }
+
+    pub fn held_buttons(&self) -> Vec<MouseButton> {
+        let mut buttons: Vec<MouseButton> = link::lock(&self.link)
+            .state()
+            .held_buttons()
+            .iter()
+            .copied()
+            .collect();
+        buttons.sort_by_key(|button| *button as u8);
+
+        buttons
+    }
+
+    pub fn release_all(&mut self) -> Result<()> {
+        let packet = link::lock(&self.link).state().release_packet();
+        self.write_as(Kind::Release, packet, Duration::ZERO)
+    }
+
+    // Panic button: releases every key the device knows, not only the ones
+    // tracked as held, for when the target's state can't be trusted
+    pub fn emergency_release(&mut self) -> Result<()> {
+        let packet = self.emergency_packet();
+        self.write_as(Kind::Release, packet, Duration::ZERO)?;
+
+        self.flush()
+    }
+
+    // Releases every held key and button, then flushes the key buffer
+    pub fn close(mut self) -> Result<()> {
+        self.closed = true;
+        self.shutdown()
+    }
+
+    fn is_held(&self, key: KeyCode) -> bool {
+        link::lock(&self.link).state().held_keys().contains(&key)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        if signal::interrupted() {
+            return link::lock(&self.link).release_all();
+        }
+
+        self.release_all()?;
+        self.flush()?;
+
+        link::lock(&self.link).finish(self.pacer.elapsed())
+    }
+
+    fn emergency_packet(&self) -> Vec<u8> {
+        let mut releases: Vec<u8> = KEY_MAP.values().map(|(_, release)| *release).collect();
+        releases.sort_unstable();
+
+        link::lock(&self.link)
+            .state()
+            .frame_padding()
+            .into_iter()
+            .chain(releases)
+            .chain(MouseAction::Move(0, 0).as_packet())
+            .collect()
+    }
+
+    // Writes in chunks, when a chunk fails and a reconnect policy is set the
+    // packet is retried from the last point where the device was back in the
+    // state the packet started from
+    fn write_packet(&mut self, packet: &[u8]) -> Result<()> {
+        let (boundaries, held) = {
+            let link = link::lock(&self.link);
+            let mut held: Vec<KeyCode> = link.state().held_keys().iter().copied().collect();
+            held.sort_by_key(|key| (!key.is_modifier(), *key as u8));
+
+            (link.state().boundaries(packet), held)
+        };
+        let mut sent = 0;
+
+        while sent < packet.len() {
+            let end = packet.len().min(sent + self.config.chunk_size);
+            match self.write_chunk(&packet[sent..end]) {
+                Ok(_) => sent = end,
+                Err(err) if self.config.reconnect.is_some() && err.is_disconnect() => {
+                    self.reconnect(err, &held)?;
+                    sent = boundaries
+                        .iter()
+                        .rev()
+                        .find(|boundary| **boundary <= sent)
+                        .copied()
+                        .unwrap_or(0);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    // `held` are the keys down before the failed packet, pressed again once
+    // the device has been reset
+    fn reconnect(&mut self, cause: Error, held: &[KeyCode]) -> Result<()> {
+        let Some(policy) = self.config.reconnect else {
+            return Err(cause);
+        };
+        self.notify(ReconnectEvent::Disconnected(cause.to_string()));
+
+        for attempt in 1..=policy.max_attempts {
+            if signal::interrupted() {
+                return Err(Error::Interrupted);
+            }
+
+            let delay = policy.backoff(attempt);
+            self.notify(ReconnectEvent::Attempt { attempt, delay });
+            thread::sleep(delay);
+
+            match self.restore(held) {
+                Ok(_) => {
+                    self.notify(ReconnectEvent::Reconnected { attempt });
+                    return Ok(());
+                }
+                Err(err) => self.notify(ReconnectEvent::Failed {
+                    attempt,
+                    error: err.to_string(),
+                }),
+            }
+        }
+
+        self.notify(ReconnectEvent::GaveUp {
+            attempts: policy.max_attempts,
+        });
+
+        Err(cause)
+    }
+
+    // Reopens the transport, puts the device back into a known-clean state
+    // and presses `held` again, since the reset also releases keys the caller
+    // is keeping down across actions
+    fn restore(&mut self, held: &[KeyCode]) -> Result<()> {
+        link::lock(&self.link).reconnect()?;
+        self.pacer.reset();
+
+        let mut packet = self.emergency_packet();
+        packet.push(FLUSH);
+        self.write_chunks(Kind::Release, &packet)?;
+
+        let presses: Vec<u8> = held.iter().map(key::press).collect();
+        self.write_chunks(Kind::Command, &presses)
+    }
+
+    // Straight to the transport, without the retry in `write_packet`
+    fn write_chunks(&mut self, kind: Kind, packet: &[u8]) -> Result<()> {
+        let previous = std::mem::replace(&mut self.action, kind);
+        let result = packet
+            .chunks(self.config.chunk_size)
+            .try_for_each(|chunk| self.write_chunk(chunk));
+        self.action = previous;
+
+        result
+    }
+
+    fn notify(&self, event: ReconnectEvent) {
+        match &self.listener {
+            Some(listener) => listener(&event),
+            None => eprintln!("{event}"),
+        }
+    }
+
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        if signal::interrupted() {
+            return Err(Error::Interrupted);
+        }
+
+        let send_at = self.pacer.schedule(chunk);
+        self.pacer.sleep_until(send_at);
+        let at = Instant::now();
+        let result = link::lock(&self.link).write(chunk);
+        self.record(at, chunk, &result);
+
+        result
+    }
+
+    fn record(&mut self, at: Instant, chunk: &[u8], result: &Result<()>) {
+        let Some(journal) = &mut self.journal else {
+            return;
+        };
+
+        let error = result.as_ref().err().map(Error::to_string);
+        if let Err(err) = journal.record(at, self.action, chunk, error.as_deref()) {
+            self.journal_failed(err);
+        }
+    }
+
+    // A broken journal shouldn't stop the device mid-action, so it's dropped
+    fn journal_failed(&mut self, err: Error) {
+        eprintln!("Journal stopped: {err}");
+        self.journal = None;
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        if let Err(err) = self.shutdown() {
+            eprintln!("Failed to release emulator: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{Device, Event};
+    use std::io;
+
+    // Fails the write after `fail_after` successful ones, once
+    struct Flaky {
+        device: Device,
+        fail_after: Option<usize>,
+    }
+
+    impl io::Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match &mut self.fail_after {
+                Some(0) => {
+                    self.fail_after = None;
+                    Err(io::ErrorKind::BrokenPipe.into())
+                }
+                Some(remaining) => {
+                    *remaining -= 1;
+                    self.device.write(buf)
+                }
+                None => self.device.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for Flaky {
+        fn reconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn reconnecting(fail_after: usize) -> (Emulator, Device) {
+        let device = Device::new();
+        let transport = Flaky {
+            device: device.clone(),
+            fail_after: Some(fail_after),
+        };
+        let emulator = Emulator::builder()
+            .chunk_size(1)
+            .reconnect(ReconnectPolicy {
+                max_attempts: 1,
+                initial_backoff: Duration::ZERO,
+                max_backoff: Duration::ZERO,
+            })
+            .on_reconnect(|_| {})
+            .build(transport)
+            .unwrap();
+
+        (emulator, device)
+    }
+
+    fn presses(events: &[Event]) -> Vec<KeyCode> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::KeyPress(key) => Some(*key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reconnect_presses_held_keys_again() {
+        let (mut emulator, device) = reconnecting(1);
+        emulator.key_down(KeyCode::Shift).unwrap();
+
+        // The first byte of the message fails, the device gets reset
+        emulator.write_message("ab", Duration::ZERO).unwrap();
+
+        assert_eq!(device.held_keys(), [KeyCode::Shift]);
+        assert_eq!(emulator.held_keys(), [KeyCode::Shift]);
+
+        let events = device.events();
+        let flush = events
+            .iter()
+            .position(|event| *event == Event::Flush)
+            .unwrap();
+        assert_eq!(
+            presses(&events[flush..]),
+            [KeyCode::Shift, KeyCode::A, KeyCode::B]
+        );
+    }
+
+    #[test]
+    fn retry_resumes_from_the_held_state() {
+        let (mut emulator, device) = reconnecting(3);
+        emulator.key_down(KeyCode::Shift).unwrap();
+
+        // Fails on the press of b, after a went down and up
+        emulator.write_message("ab", Duration::ZERO).unwrap();
+
+        let events = device.events();
+        assert_eq!(
+            presses(&events),
+            [KeyCode::Shift, KeyCode::A, KeyCode::Shift, KeyCode::B]
+        );
+        assert_eq!(device.held_keys(), [KeyCode::Shift]);
+    }
+}
@@ -0,0 +1,61 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // Doubles with every attempt, starting from `initial_backoff`
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    Disconnected(String),
+    Attempt { attempt: u32, delay: Duration },
+    Failed { attempt: u32, error: String },
+    Reconnected { attempt: u32 },
+    GaveUp { attempts: u32 },
+}
+
+impl fmt::Display for ReconnectEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected(error) => write!(f, "Emulator disconnected: {error}"),
+            Self::Attempt { attempt, delay } => {
+                write!(f, "Reconnect attempt {attempt} in {}ms", delay.as_millis())
+            }
+            Self::Failed { attempt, error } => {
+                write!(f, "Reconnect attempt {attempt} failed: {error}")
+            }
+            Self::Reconnected { attempt } => write!(f, "Reconnected on attempt {attempt}"),
+            Self::GaveUp { attempts } => {
+                write!(f, "Gave up reconnecting after {attempts} attempts")
+            }
+        }
+    }
+}
+
+pub type ReconnectListener = Arc<dyn Fn(&ReconnectEvent) + Send + Sync>;
@@ -1,3 +1,4 @@
+use crate::action::key::UnsupportedChar;
 use std::sync::PoisonError;
 use thiserror::Error;
 
@@ -9,12 +10,79 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     SerialPort(#[from] serialport::Error),
+    #[error(transparent)]
+    Signal(#[from] ctrlc::Error),
+    #[error("Interrupted by signal")]
+    Interrupted,
+    #[error("No device with serial number {0}")]
+    DeviceNotFound(String),
+    #[error("{} emulator(s) in the group failed", .0.len())]
+    Group(Vec<(usize, Error)>),
+    #[error("Emulator thread panicked")]
+    Panicked,
+    #[error("Device is in use by another client")]
+    Busy,
+    #[error("Authentication failed")]
+    Unauthorized,
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+    #[error("Remote error: {0}")]
+    Remote(String),
+    #[error("Journal line {line}: {message}")]
+    Journal { line: usize, message: String },
+    #[error("No marker named {0:?}")]
+    MarkerNotFound(String),
+    #[error("Replay aborted")]
+    Aborted,
+    #[error("Can't type {}", list(.0))]
+    UnsupportedChars(Vec<UnsupportedChar>),
+    #[error("Invalid chord: {0}")]
+    Chord(String),
+    #[error("Invalid key sequence at {start}..{end}: {message}")]
+    Sequence {
+        start: usize,
+        end: usize,
+        message: String,
+    },
+    #[error("{location}: {message}")]
+    Script { location: String, message: String },
+    #[error("Invalid configuration: {0}")]
+    Config(String),
     #[error("Lock poisoned: {0}")]
     Poison(String),
 }
 
+impl Error {
+    // Failures that a reconnect might recover from
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, Self::Io(_) | Self::SerialPort(_))
+    }
+
+    // The device or port doesn't exist, as opposed to failing once opened
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Self::DeviceNotFound(_) => true,
+            Self::Io(err) => err.kind() == std::io::ErrorKind::NotFound,
+            Self::SerialPort(err) => matches!(
+                err.kind,
+                serialport::ErrorKind::NoDevice
+                    | serialport::ErrorKind::Io(std::io::ErrorKind::NotFound)
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl<T> From<PoisonError<T>> for Error {
     fn from(err: PoisonError<T>) -> Self {
         Self::Poison(err.to_string())
     }
 }
+
+fn list(chars: &[UnsupportedChar]) -> String {
+    chars
+        .iter()
+        .map(UnsupportedChar::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
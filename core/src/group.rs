@@ -0,0 +1,249 @@
+// Fans the same input out to several emulators, one thread per device
+
+use crate::{
+    error::{Error, Result},
+    Emulator,
+};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Barrier,
+    },
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    // Any failure stops every device at its next action and fails the call
+    #[default]
+    AllMustSucceed,
+    // Failed devices drop out, the rest carry on
+    BestEffort,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    pub packet: Vec<u8>,
+    pub sleep_duration: Duration,
+}
+
+impl Action {
+    pub fn new(packet: Vec<u8>, sleep_duration: Duration) -> Self {
+        Self {
+            packet,
+            sleep_duration,
+        }
+    }
+}
+
+// Outcome per device, in the order the emulators were added
+#[derive(Debug)]
+pub struct Report(Vec<Result<()>>);
+
+impl Report {
+    pub fn results(&self) -> &[Result<()>] {
+        &self.0
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.0.iter().all(Result::is_ok)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (usize, &Error)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, result)| result.as_ref().err().map(|err| (i, err)))
+    }
+}
+
+#[derive(Default)]
+pub struct EmulatorGroup {
+    emulators: Vec<Emulator>,
+    mode: Mode,
+    lockstep: bool,
+}
+
+impl EmulatorGroup {
+    pub fn new(emulators: Vec<Emulator>) -> Self {
+        Self {
+            emulators,
+            ..Default::default()
+        }
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // Every device finishes action N before any of them starts action N + 1
+    pub fn lockstep(mut self, lockstep: bool) -> Self {
+        self.lockstep = lockstep;
+        self
+    }
+
+    pub fn push(&mut self, emulator: Emulator) {
+        self.emulators.push(emulator);
+    }
+
+    pub fn len(&self) -> usize {
+        self.emulators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emulators.is_empty()
+    }
+
+    pub fn emulators_mut(&mut self) -> &mut [Emulator] {
+        &mut self.emulators
+    }
+
+    pub fn into_inner(self) -> Vec<Emulator> {
+        self.emulators
+    }
+
+    pub fn write(&mut self, packet: Vec<u8>, sleep_duration: Duration) -> Result<Report> {
+        self.run(&[Action::new(packet, sleep_duration)])
+    }
+
+    pub fn run(&mut self, actions: &[Action]) -> Result<Report> {
+        self.run_with(actions.len(), |i, emulator| {
+            emulator.write(actions[i].packet.clone(), actions[i].sleep_duration)
+        })
+    }
+
+    pub fn for_each<F>(&mut self, callback: F) -> Result<Report>
+    where
+        F: Fn(&mut Emulator) -> Result<()> + Sync,
+    {
+        self.run_with(1, |_, emulator| callback(emulator))
+    }
+
+    // Calls `action(i, emulator)` for i in 0..steps on every device
+    pub fn run_with<F>(&mut self, steps: usize, action: F) -> Result<Report>
+    where
+        F: Fn(usize, &mut Emulator) -> Result<()> + Sync,
+    {
+        let aborted = AtomicBool::new(false);
+        let barrier = Barrier::new(self.emulators.len());
+        let (mode, lockstep) = (self.mode, self.lockstep);
+
+        let results: Vec<Result<()>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .emulators
+                .iter_mut()
+                .map(|emulator| {
+                    let (aborted, barrier, action) = (&aborted, &barrier, &action);
+
+                    scope.spawn(move || {
+                        let mut result = Ok(());
+                        for i in 0..steps {
+                            if result.is_ok() && !aborted.load(Ordering::SeqCst) {
+                                // A panic has to count as a failure here,
+                                // or the device would stop meeting the barrier
+                                result =
+                                    panic::catch_unwind(AssertUnwindSafe(|| action(i, emulator)))
+                                        .unwrap_or(Err(Error::Panicked));
+                                if result.is_err() && mode == Mode::AllMustSucceed {
+                                    aborted.store(true, Ordering::SeqCst);
+                                }
+                            }
+
+                            // Failed devices keep meeting the barrier so the
+                            // others aren't left waiting on them
+                            if lockstep {
+                                barrier.wait();
+                            }
+                        }
+
+                        result
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or(Err(Error::Panicked)))
+                .collect()
+        });
+
+        let report = Report(results);
+        match mode {
+            Mode::AllMustSucceed if !report.is_success() => Err(Error::Group(
+                report
+                    .0
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, result)| result.err().map(|err| (i, err)))
+                    .collect(),
+            )),
+            _ => Ok(report),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{atomic::AtomicUsize, mpsc},
+        thread,
+    };
+
+    #[test]
+    fn lockstep_survives_a_panicking_device() {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut group = EmulatorGroup::new(vec![Emulator::dry_run(), Emulator::dry_run()])
+                .lockstep(true)
+                .mode(Mode::BestEffort);
+            let calls = AtomicUsize::new(0);
+            let panicked = AtomicBool::new(false);
+
+            let report = group.run_with(3, |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                if !panicked.swap(true, Ordering::SeqCst) {
+                    panic!("device failed");
+                }
+                Ok(())
+            });
+            let _ = sender.send((report, calls.load(Ordering::SeqCst)));
+        });
+
+        let (report, calls) = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("group deadlocked after a panic");
+        let report = report.unwrap();
+        let failures: Vec<_> = report.failures().collect();
+
+        assert_eq!(failures.len(), 1);
+        assert!(matches!(failures[0].1, Error::Panicked));
+        // The healthy device carries on through every step
+        assert_eq!(calls, 4);
+    }
+
+    #[test]
+    fn panic_fails_an_all_must_succeed_group() {
+        let mut group =
+            EmulatorGroup::new(vec![Emulator::dry_run(), Emulator::dry_run()]).lockstep(true);
+        let panicked = AtomicBool::new(false);
+
+        let result = group.run_with(2, |_, _| {
+            if !panicked.swap(true, Ordering::SeqCst) {
+                panic!("device failed");
+            }
+            Ok(())
+        });
+
+        match result {
+            Err(Error::Group(failures)) => {
+                assert_eq!(failures.len(), 1);
+                assert!(matches!(failures[0].1, Error::Panicked));
+            }
+            other => panic!("expected a group error, got {other:?}"),
+        }
+    }
+}
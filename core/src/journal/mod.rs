@@ -0,0 +1,199 @@
+// Append-only record of everything an emulator sends
+//
+// A journal is a UTF-8 text file. The first line is a header:
+//
+//     # hagstrom journal v1 <unix seconds when recording started>
+//
+// followed by one line per chunk written to the transport:
+//
+//     <microseconds since start>\t<kind>\t<hex bytes>\t<detail>
+//
+// `kind` names the action that produced the chunk, one of message, command,
+// mouse, flush, release, raw or marker. `detail` is the error the write
+// failed with, or the name of a marker, and is empty otherwise. Tabs,
+// newlines, carriage returns and backslashes in it are escaped as \t, \n, \r
+// and \\. Other lines starting with `#` are comments.
+//
+// Journals are only ever appended to. Reopening one adds a
+//
+//     # resumed <unix seconds>
+//
+// comment and carries on from the time of its last entry, so separate
+// recording sessions replay back to back. A last line left unfinished or
+// unreadable by a crash is cut off first.
+
+mod reader;
+mod replay;
+mod writer;
+
+pub use reader::{read, Reader};
+pub use replay::{Position, Replay, Speed, Summary};
+pub use writer::Writer;
+
+use crate::error::{Error, Result};
+use std::{fmt, str::FromStr, time::Duration};
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Message,
+    Command,
+    Mouse,
+    Flush,
+    Release,
+    #[default]
+    Raw,
+    // Named position in the journal, carries no bytes
+    Marker,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Message => "message",
+            Self::Command => "command",
+            Self::Mouse => "mouse",
+            Self::Flush => "flush",
+            Self::Release => "release",
+            Self::Raw => "raw",
+            Self::Marker => "marker",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "message" => Self::Message,
+            "command" => Self::Command,
+            "mouse" => Self::Mouse,
+            "flush" => Self::Flush,
+            "release" => Self::Release,
+            "raw" => Self::Raw,
+            "marker" => Self::Marker,
+            _ => return Err(format!("unknown kind {s:?}")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // Since the journal was started
+    pub time: Duration,
+    pub kind: Kind,
+    pub bytes: Vec<u8>,
+    pub detail: Option<String>,
+}
+
+impl Entry {
+    pub fn error(&self) -> Option<&str> {
+        match self.kind {
+            Kind::Marker => None,
+            _ => self.detail.as_deref(),
+        }
+    }
+
+    pub fn marker(&self) -> Option<&str> {
+        match self.kind {
+            Kind::Marker => self.detail.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let hex: String = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let detail = self.detail.as_deref().map(escape).unwrap_or_default();
+
+        format!(
+            "{}\t{}\t{hex}\t{detail}\n",
+            self.time.as_micros(),
+            self.kind
+        )
+    }
+
+    fn parse(line: &str, number: usize) -> Result<Self> {
+        let error = |message: String| Error::Journal {
+            line: number,
+            message,
+        };
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [time, kind, hex, detail] = fields[..] else {
+            return Err(error(format!("expected 4 fields, found {}", fields.len())));
+        };
+
+        let time = time
+            .parse()
+            .map(Duration::from_micros)
+            .map_err(|_| error(format!("invalid timestamp {time:?}")))?;
+        let kind = kind.parse().map_err(error)?;
+        if hex.len() % 2 != 0 {
+            return Err(error("odd number of hex digits".to_owned()));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| error(format!("invalid hex {hex:?}")))?;
+        let detail = match detail.is_empty() {
+            true => None,
+            false => Some(unescape(detail)),
+        };
+
+        Ok(Self {
+            time,
+            kind,
+            bytes,
+            detail,
+        })
+    }
+}
+
+fn escape(detail: &str) -> String {
+    let mut escaped = String::with_capacity(detail.len());
+    for char in detail.chars() {
+        match char {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(detail: &str) -> String {
+    let mut unescaped = String::with_capacity(detail.len());
+    let mut chars = detail.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(char) => unescaped.push(char),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
@@ -0,0 +1,69 @@
+use super::{Entry, VERSION};
+use crate::error::{Error, Result};
+use std::{
+    fs,
+    io::{BufRead, BufReader, Lines},
+    path::Path,
+};
+
+pub struct Reader<R> {
+    lines: Lines<R>,
+    number: usize,
+    started: u64,
+}
+
+impl Reader<BufReader<fs::File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufReader::new(fs::File::open(path)?))
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+
+        let started = header
+            .strip_prefix(&format!("# hagstrom journal v{VERSION} "))
+            .and_then(|started| started.trim().parse().ok())
+            .ok_or_else(|| Error::Journal {
+                line: 1,
+                message: format!("expected a v{VERSION} header"),
+            })?;
+
+        Ok(Self {
+            lines,
+            number: 1,
+            started,
+        })
+    }
+
+    // Unix seconds when recording started
+    pub fn started(&self) -> u64 {
+        self.started
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.number += 1;
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            return Some(Entry::parse(&line, self.number));
+        }
+    }
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<Entry>> {
+    Reader::open(path)?.collect()
+}
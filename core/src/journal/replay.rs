@@ -0,0 +1,488 @@
+use super::{read, Entry, Kind};
+use crate::{
+    error::{Error, Result},
+    signal, Emulator,
+};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+// How often a long wait between entries checks for an abort
+const ABORT_POLL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Speed {
+    // Same gaps between entries as when they were recorded
+    #[default]
+    Original,
+    // 2.0 replays twice as fast, 0.5 at half speed
+    Scaled(f64),
+    // No gaps, only as fast as the emulator's pacing lets the device keep up
+    Compressed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    // Since the journal was started
+    Offset(Duration),
+    Marker(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub entries: usize,
+    pub bytes: usize,
+    pub elapsed: Duration,
+}
+
+// Sends a recorded journal to another emulator. Entries that failed when
+// recorded are skipped since there's no telling what reached the device, and
+// markers are copied into the target's own journal.
+pub struct Replay {
+    entries: Vec<Entry>,
+    speed: Speed,
+    start: Option<Position>,
+    stop: Option<Position>,
+}
+
+impl Replay {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            speed: Speed::default(),
+            start: None,
+            stop: None,
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(read(path)?))
+    }
+
+    pub fn speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn start_at(mut self, position: Position) -> Self {
+        self.start = Some(position);
+        self
+    }
+
+    // Breakpoint, the entry at `position` and everything after it is left out
+    pub fn stop_at(mut self, position: Position) -> Self {
+        self.stop = Some(position);
+        self
+    }
+
+    pub fn run(&self, emulator: &mut Emulator) -> Result<Summary> {
+        self.run_until(emulator, &AtomicBool::new(false))
+    }
+
+    // Stops at the next entry once `abort` is set. However the replay ends
+    // early, at a breakpoint, an abort or an error, held keys and buttons are
+    // released before returning.
+    pub fn run_until(&self, emulator: &mut Emulator, abort: &AtomicBool) -> Result<Summary> {
+        if let Speed::Scaled(factor) = self.speed {
+            if !factor.is_finite() || factor <= 0.0 {
+                return Err(Error::Config(format!("replay speed {factor}")));
+            }
+        }
+
+        let (entries, cut) = self.selection()?;
+        match self.play(entries, emulator, abort) {
+            // Whatever was pressed before the breakpoint may only be released
+            // after it
+            Ok(summary) if cut => emulator.release_all().map(|_| summary),
+            Ok(summary) => Ok(summary),
+            Err(err) => {
+                if !signal::interrupted() {
+                    let _ = emulator.release_all();
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    // The entries to play and whether a breakpoint left some out at the end
+    fn selection(&self) -> Result<(&[Entry], bool)> {
+        let start = match &self.start {
+            Some(position) => self.find(position, 0)?,
+            None => 0,
+        };
+        let end = match &self.stop {
+            Some(position) => self.find(position, start)?,
+            None => self.entries.len(),
+        };
+
+        Ok((&self.entries[start..end], end < self.entries.len()))
+    }
+
+    fn find(&self, position: &Position, from: usize) -> Result<usize> {
+        let entries = &self.entries[from..];
+        let index = match position {
+            Position::Offset(offset) => entries
+                .iter()
+                .position(|entry| entry.time >= *offset)
+                .unwrap_or(entries.len()),
+            Position::Marker(name) => entries
+                .iter()
+                .position(|entry| entry.marker() == Some(name))
+                .ok_or_else(|| Error::MarkerNotFound(name.clone()))?,
+        };
+
+        Ok(from + index)
+    }
+
+    fn play(
+        &self,
+        entries: &[Entry],
+        emulator: &mut Emulator,
+        abort: &AtomicBool,
+    ) -> Result<Summary> {
+        let start = Instant::now();
+        let mut summary = Summary::default();
+        let Some(first) = entries.first() else {
+            return Ok(summary);
+        };
+
+        for entry in entries {
+            let offset = entry.time.saturating_sub(first.time);
+            let deadline = match self.speed {
+                Speed::Original => Some(start + offset),
+                Speed::Scaled(factor) => Some(start + offset.div_f64(factor)),
+                Speed::Compressed => None,
+            };
+            if let Some(deadline) = deadline {
+                wait_until(deadline, abort)?;
+            }
+            if abort.load(Ordering::SeqCst) {
+                return Err(Error::Aborted);
+            }
+
+            match (entry.kind, entry.marker()) {
+                (Kind::Marker, Some(name)) => emulator.mark(name),
+                (Kind::Marker, None) => {}
+                _ if entry.error().is_some() => continue,
+                (kind, _) => emulator.write_as(kind, entry.bytes.clone(), Duration::ZERO)?,
+            }
+
+            summary.entries += 1;
+            summary.bytes += entry.bytes.len();
+        }
+        summary.elapsed = start.elapsed();
+
+        Ok(summary)
+    }
+}
+
+fn wait_until(deadline: Instant, abort: &AtomicBool) -> Result<()> {
+    loop {
+        if abort.load(Ordering::SeqCst) {
+            return Err(Error::Aborted);
+        }
+        if signal::interrupted() {
+            return Err(Error::Interrupted);
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+        thread::sleep(ABORT_POLL.min(deadline - now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{key, KeyCode},
+        sim::{Device, Event},
+        transport::Transport,
+    };
+    use std::{io, sync::Arc};
+
+    // Fails the `fail_on`th write, counting from 0, and nothing else
+    struct Failing {
+        device: Device,
+        writes: usize,
+        fail_on: usize,
+    }
+
+    impl io::Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            match self.writes - 1 == self.fail_on {
+                true => Err(io::ErrorKind::BrokenPipe.into()),
+                false => self.device.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for Failing {}
+
+    fn recording(fail_on: usize) -> (Emulator, Device) {
+        let device = Device::new();
+        let transport = Failing {
+            device: device.clone(),
+            writes: 0,
+            fail_on,
+        };
+
+        (Emulator::builder().build(transport).unwrap(), device)
+    }
+
+    fn press(millis: u64, key: KeyCode) -> Entry {
+        entry(millis, Kind::Command, vec![key::press(&key)], None)
+    }
+
+    fn release(millis: u64, key: KeyCode) -> Entry {
+        entry(millis, Kind::Command, vec![key::release(&key)], None)
+    }
+
+    fn marker(millis: u64, name: &str) -> Entry {
+        entry(millis, Kind::Marker, vec![], Some(name))
+    }
+
+    fn presses(device: &Device) -> Vec<KeyCode> {
+        device
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::KeyPress(key) => Some(key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Shift is pressed and released again, and nothing is left held
+    fn released_shift(device: &Device) -> bool {
+        let events = device.events();
+        let pressed = events
+            .iter()
+            .position(|e| *e == Event::KeyPress(KeyCode::Shift));
+        let released = events
+            .iter()
+            .rposition(|e| *e == Event::KeyRelease(KeyCode::Shift));
+
+        matches!((pressed, released), (Some(p), Some(r)) if p < r) && device.held_keys().is_empty()
+    }
+
+    fn entry(millis: u64, kind: Kind, bytes: Vec<u8>, detail: Option<&str>) -> Entry {
+        Entry {
+            time: Duration::from_millis(millis),
+            kind,
+            bytes,
+            detail: detail.map(str::to_owned),
+        }
+    }
+
+    // Shift down, a marker, then Shift up
+    fn shift_around_marker() -> Vec<Entry> {
+        vec![
+            entry(0, Kind::Command, vec![44], None),
+            entry(10, Kind::Marker, vec![], Some("middle")),
+            entry(20, Kind::Command, vec![172], None),
+        ]
+    }
+
+    #[test]
+    fn breakpoint_releases_held_keys() {
+        let mut emulator = Emulator::dry_run();
+        let summary = Replay::new(shift_around_marker())
+            .speed(Speed::Compressed)
+            .stop_at(Position::Marker("middle".to_owned()))
+            .run(&mut emulator)
+            .unwrap();
+
+        assert_eq!(summary.entries, 1);
+        assert!(emulator.held_keys().is_empty());
+    }
+
+    #[test]
+    fn full_replay_leaves_state_alone() {
+        let mut entries = shift_around_marker();
+        entries.pop();
+
+        let mut emulator = Emulator::dry_run();
+        Replay::new(entries)
+            .speed(Speed::Compressed)
+            .run(&mut emulator)
+            .unwrap();
+
+        assert_eq!(emulator.held_keys(), vec![KeyCode::Shift]);
+    }
+
+    #[test]
+    fn scaled_speed_shortens_gaps() {
+        let entries = vec![press(0, KeyCode::A), release(200, KeyCode::A)];
+
+        let mut emulator = Emulator::dry_run();
+        let summary = Replay::new(entries)
+            .speed(Speed::Scaled(4.0))
+            .run(&mut emulator)
+            .unwrap();
+
+        assert_eq!(summary.entries, 2);
+        assert!(summary.elapsed >= Duration::from_millis(50), "{summary:?}");
+        assert!(summary.elapsed < Duration::from_millis(200), "{summary:?}");
+    }
+
+    #[test]
+    fn compressed_speed_skips_gaps() {
+        let entries = vec![press(0, KeyCode::A), release(60_000, KeyCode::A)];
+
+        let mut emulator = Emulator::dry_run();
+        let summary = Replay::new(entries)
+            .speed(Speed::Compressed)
+            .run(&mut emulator)
+            .unwrap();
+
+        assert!(summary.elapsed < Duration::from_secs(1), "{summary:?}");
+    }
+
+    #[test]
+    fn bad_scale_is_a_config_error() {
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let result = Replay::new(vec![])
+                .speed(Speed::Scaled(factor))
+                .run(&mut Emulator::dry_run());
+            assert!(matches!(result, Err(Error::Config(_))), "{factor}");
+        }
+    }
+
+    #[test]
+    fn start_at_offset_skips_earlier_entries() {
+        let entries = vec![
+            press(0, KeyCode::A),
+            press(10, KeyCode::B),
+            press(20, KeyCode::C),
+        ];
+
+        let (mut emulator, device) = recording(usize::MAX);
+        Replay::new(entries)
+            .speed(Speed::Compressed)
+            .start_at(Position::Offset(Duration::from_millis(10)))
+            .run(&mut emulator)
+            .unwrap();
+
+        assert_eq!(presses(&device), [KeyCode::B, KeyCode::C]);
+    }
+
+    #[test]
+    fn start_and_stop_at_markers() {
+        let entries = vec![
+            press(0, KeyCode::A),
+            marker(10, "from"),
+            press(20, KeyCode::B),
+            marker(30, "to"),
+            press(40, KeyCode::C),
+        ];
+
+        let (mut emulator, device) = recording(usize::MAX);
+        let summary = Replay::new(entries)
+            .speed(Speed::Compressed)
+            .start_at(Position::Marker("from".to_owned()))
+            .stop_at(Position::Marker("to".to_owned()))
+            .run(&mut emulator)
+            .unwrap();
+
+        assert_eq!(summary.entries, 2);
+        assert_eq!(presses(&device), [KeyCode::B]);
+    }
+
+    #[test]
+    fn missing_marker_sends_nothing() {
+        let (mut emulator, device) = recording(usize::MAX);
+        let result = Replay::new(shift_around_marker())
+            .stop_at(Position::Marker("nowhere".to_owned()))
+            .run(&mut emulator);
+
+        assert!(matches!(result, Err(Error::MarkerNotFound(_))));
+        assert!(device.events().is_empty());
+    }
+
+    #[test]
+    fn breakpoint_sends_release_frames() {
+        let (mut emulator, device) = recording(usize::MAX);
+        Replay::new(shift_around_marker())
+            .speed(Speed::Compressed)
+            .stop_at(Position::Marker("middle".to_owned()))
+            .run(&mut emulator)
+            .unwrap();
+
+        assert!(released_shift(&device), "{:?}", device.events());
+    }
+
+    #[test]
+    fn abort_sends_release_frames() {
+        let entries = vec![press(0, KeyCode::Shift), release(60_000, KeyCode::Shift)];
+        let abort = Arc::new(AtomicBool::new(false));
+        let setter = {
+            let abort = abort.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                abort.store(true, Ordering::SeqCst);
+            })
+        };
+
+        let (mut emulator, device) = recording(usize::MAX);
+        let result = Replay::new(entries).run_until(&mut emulator, &abort);
+        setter.join().unwrap();
+
+        assert!(matches!(result, Err(Error::Aborted)));
+        assert!(released_shift(&device), "{:?}", device.events());
+        assert!(emulator.held_keys().is_empty());
+    }
+
+    #[test]
+    fn write_error_sends_release_frames() {
+        let entries = vec![
+            press(0, KeyCode::Shift),
+            press(10, KeyCode::A),
+            release(20, KeyCode::A),
+            release(30, KeyCode::Shift),
+        ];
+
+        // The press of A fails
+        let (mut emulator, device) = recording(1);
+        let result = Replay::new(entries)
+            .speed(Speed::Compressed)
+            .run(&mut emulator);
+
+        assert!(matches!(result, Err(Error::Io(_))));
+        assert!(released_shift(&device), "{:?}", device.events());
+    }
+
+    #[test]
+    fn failed_entries_are_skipped() {
+        let entries = vec![
+            press(0, KeyCode::A),
+            entry(
+                10,
+                Kind::Command,
+                vec![key::press(&KeyCode::B)],
+                Some("broken pipe"),
+            ),
+            press(20, KeyCode::C),
+        ];
+
+        let (mut emulator, device) = recording(usize::MAX);
+        let summary = Replay::new(entries)
+            .speed(Speed::Compressed)
+            .run(&mut emulator)
+            .unwrap();
+
+        assert_eq!(summary.entries, 2);
+        assert_eq!(presses(&device), [KeyCode::A, KeyCode::C]);
+    }
+}
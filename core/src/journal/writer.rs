@@ -0,0 +1,231 @@
+use super::{Entry, Kind, Reader, VERSION};
+use crate::error::Result;
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// Every entry is written straight through so a crash loses nothing
+pub struct Writer {
+    file: fs::File,
+    path: PathBuf,
+    start: Instant,
+    // Time of the last entry already in the file when it was reopened
+    offset: Duration,
+}
+
+impl Writer {
+    // Appends to an existing journal, anything else at `path` is an error
+    // rather than something to overwrite
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let existing = fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0);
+
+        let offset = match existing {
+            true => resume(&path)?,
+            false => Duration::ZERO,
+        };
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        match existing {
+            true => writeln!(file, "# resumed {}", unix_now())?,
+            false => writeln!(file, "# hagstrom journal v{VERSION} {}", unix_now())?,
+        }
+
+        Ok(Self {
+            file,
+            path,
+            start: Instant::now(),
+            offset,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // `at` is when the bytes went out, not when this gets called
+    pub fn record(
+        &mut self,
+        at: Instant,
+        kind: Kind,
+        bytes: &[u8],
+        detail: Option<&str>,
+    ) -> Result<()> {
+        let entry = Entry {
+            time: self.offset + at.saturating_duration_since(self.start),
+            kind,
+            bytes: bytes.to_vec(),
+            detail: detail.map(str::to_owned),
+        };
+        self.file.write_all(entry.to_line().as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn mark(&mut self, name: &str) -> Result<()> {
+        self.record(Instant::now(), Kind::Marker, &[], Some(name))
+    }
+}
+
+// Time of the last entry in the journal at `path`. A crash mid-write can
+// leave the last line unfinished or unreadable, that line is cut off, a bad
+// line anywhere else means the file isn't a journal.
+fn resume(path: &Path) -> Result<Duration> {
+    let contents = fs::read(path)?;
+    let complete = after_last_newline(&contents);
+
+    let (keep, offset) = match last_time(&contents[..complete]) {
+        Ok(offset) => (complete, offset),
+        Err(_) => {
+            let previous = after_last_newline(&contents[..complete.saturating_sub(1)]);
+            (previous, last_time(&contents[..previous])?)
+        }
+    };
+    if keep < contents.len() {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(keep as u64)?;
+    }
+
+    Ok(offset)
+}
+
+fn last_time(journal: &[u8]) -> Result<Duration> {
+    Reader::new(journal)?.try_fold(Duration::ZERO, |last, entry| {
+        entry.map(|entry| last.max(entry.time))
+    })
+}
+
+fn after_last_newline(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |index| index + 1)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::read;
+    use std::process;
+
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("hagstrom-journal-{}-{name}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        path
+    }
+
+    #[test]
+    fn reopening_appends() {
+        let path = path("append");
+
+        let mut writer = Writer::open(&path).unwrap();
+        writer
+            .record(Instant::now(), Kind::Command, &[44], None)
+            .unwrap();
+        writer.mark("first").unwrap();
+        drop(writer);
+
+        let mut writer = Writer::open(&path).unwrap();
+        writer
+            .record(Instant::now(), Kind::Command, &[172], None)
+            .unwrap();
+        drop(writer);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.matches("# hagstrom journal").count(), 1);
+        assert_eq!(contents.matches("# resumed").count(), 1);
+
+        let entries = read(&path).unwrap();
+        let bytes: Vec<_> = entries.iter().map(|entry| entry.bytes.clone()).collect();
+        assert_eq!(bytes, [vec![44], vec![], vec![172]]);
+        assert!(entries.windows(2).all(|pair| pair[0].time <= pair[1].time));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    fn journal_with(path: &Path, tail: &str) {
+        let mut writer = Writer::open(path).unwrap();
+        writer
+            .record(Instant::now(), Kind::Command, &[44], None)
+            .unwrap();
+        drop(writer);
+
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(tail.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn resuming_cuts_off_an_unfinished_line() {
+        let path = path("unfinished");
+        journal_with(&path, "1234\tcomm");
+
+        let mut writer = Writer::open(&path).unwrap();
+        writer
+            .record(Instant::now(), Kind::Command, &[172], None)
+            .unwrap();
+        drop(writer);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("1234\tcomm"));
+        let bytes: Vec<_> = read(&path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.bytes)
+            .collect();
+        assert_eq!(bytes, [vec![44], vec![172]]);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn resuming_cuts_off_an_unreadable_last_line() {
+        let path = path("unreadable");
+        journal_with(&path, "12\tnonsense\tzz\t\n");
+
+        Writer::open(&path).unwrap();
+        assert_eq!(read(&path).unwrap().len(), 1);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn resuming_keeps_bad_lines_before_the_last() {
+        let path = path("corrupt");
+        journal_with(&path, "12\tnonsense\tzz\t\n");
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"20\tcommand\t2c\t\n").unwrap();
+        let before = fs::read(&path).unwrap();
+
+        assert!(Writer::open(&path).is_err());
+        assert_eq!(fs::read(&path).unwrap(), before);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_append_to_other_files() {
+        let path = path("other");
+        fs::write(&path, "not a journal\n").unwrap();
+
+        assert!(Writer::open(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not a journal\n");
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,8 +1,20 @@
 pub mod action;
+pub mod bridge;
+pub mod discovery;
 mod emulator;
 pub mod error;
+pub mod group;
+pub mod journal;
+pub mod pacing;
+pub mod script;
+pub mod signal;
+pub mod sim;
+pub mod transport;
 
 // pub use action::{KeyCode, MouseAction, ScrollDirection, ScrollMagnitude};
-pub use emulator::Emulator;
+pub use emulator::{Emulator, EmulatorBuilder, ReconnectEvent, ReconnectPolicy};
+
+pub use discovery::discover;
+pub use group::EmulatorGroup;
 
 pub const FLUSH: u8 = 0x38;
@@ -0,0 +1,175 @@
+// Schedules writes against a model of the device: bytes cross the serial line
+// at `line_rate`, wait in an input buffer of `buffer_size` bytes and are
+// drained one HID report at a time at `report_rate`. Every deadline is an
+// absolute `Instant`, so rounding never accumulates into drift.
+//
+// A virtual pacer never sleeps, its clock jumps ahead to whatever it is asked
+// to wait for. Dry runs use it to estimate how long the real device would take.
+
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+};
+
+const MOUSE_FRAME: u8 = 0x00;
+const MOUSE_FRAME_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pacing {
+    pub buffer_size: usize,
+    pub report_rate: u32,
+    pub line_rate: u32,
+}
+
+// The Hagstrom documentation gives neither the input buffer size nor how
+// fast reports go out, so the buffer and report rate are conservative
+// guesses: a small buffer and a rate well under the 1000 Hz USB full speed
+// allows. Too low only costs speed, too high drops bytes once the buffer
+// fills. Tune them with `EmulatorBuilder::buffer_size` and `report_rate`,
+// raising them until the target starts missing keys. The line rate is 19200
+// baud at 10 bits per 8N1 byte, the builder derives it from the serial
+// settings.
+impl Default for Pacing {
+    fn default() -> Self {
+        Self {
+            buffer_size: 32,
+            report_rate: 250,
+            line_rate: 1_920,
+        }
+    }
+}
+
+pub struct Pacer {
+    pacing: Pacing,
+    // (Processed at, Bytes) for every report still held by the device
+    reports: VecDeque<(Instant, usize)>,
+    // Bytes of a mouse frame whose remainder hasn't been sent yet
+    partial: usize,
+    arrived: Instant,
+    drained: Instant,
+    start: Instant,
+    clock: Option<Instant>,
+}
+
+impl Pacer {
+    pub fn new(pacing: Pacing) -> Self {
+        Self::starting_at(pacing, Instant::now(), false)
+    }
+
+    pub fn new_virtual(pacing: Pacing) -> Self {
+        Self::starting_at(pacing, Instant::now(), true)
+    }
+
+    fn starting_at(pacing: Pacing, now: Instant, simulated: bool) -> Self {
+        Self {
+            pacing,
+            reports: VecDeque::new(),
+            partial: 0,
+            arrived: now,
+            drained: now,
+            start: now,
+            clock: simulated.then_some(now),
+        }
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.clock.is_some()
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    // Reserves room for `chunk` and returns when it may be written
+    pub fn schedule(&mut self, chunk: &[u8]) -> Instant {
+        let now = self.now();
+        while matches!(self.reports.front(), Some((done, _)) if *done <= now) {
+            self.reports.pop_front();
+        }
+
+        let mut send_at = now;
+        let mut occupied = self.occupied();
+        while occupied + chunk.len() > self.pacing.buffer_size {
+            let Some((done, bytes)) = self.reports.pop_front() else {
+                break;
+            };
+            send_at = send_at.max(done);
+            occupied -= bytes;
+        }
+
+        let byte_time = Duration::from_secs(1) / self.pacing.line_rate;
+        let report_time = Duration::from_secs(1) / self.pacing.report_rate;
+        chunk.iter().for_each(|byte| {
+            self.arrived = self.arrived.max(send_at) + byte_time;
+
+            let bytes = match self.partial {
+                0 if *byte == MOUSE_FRAME => 1,
+                0 => {
+                    self.complete(1, report_time);
+                    return;
+                }
+                partial => partial + 1,
+            };
+
+            if bytes == MOUSE_FRAME_LEN {
+                self.partial = 0;
+                self.complete(bytes, report_time);
+            } else {
+                self.partial = bytes;
+            }
+        });
+
+        send_at
+    }
+
+    // When the device will have processed everything scheduled so far
+    pub fn drained(&self) -> Instant {
+        self.drained.max(self.arrived)
+    }
+
+    // Waits for the device to go idle, then for `duration` on top of that
+    pub fn wait(&mut self, duration: Duration) {
+        self.sleep_until(self.drained() + duration);
+    }
+
+    pub fn sleep_until(&mut self, deadline: Instant) {
+        match &mut self.clock {
+            Some(now) => *now = (*now).max(deadline),
+            None => sleep_until(deadline),
+        }
+    }
+
+    // From creation until the device has processed everything and any wait
+    // asked for has passed
+    pub fn elapsed(&self) -> Duration {
+        self.now().max(self.drained()) - self.start
+    }
+
+    // Forgets what the device holds, a virtual clock keeps its time
+    pub fn reset(&mut self) {
+        let start = self.start;
+        *self = Self::starting_at(self.pacing, self.now(), self.is_virtual());
+        self.start = start;
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.unwrap_or_else(Instant::now)
+    }
+
+    fn complete(&mut self, bytes: usize, report_time: Duration) {
+        self.drained = self.drained.max(self.arrived) + report_time;
+        self.reports.push_back((self.drained, bytes));
+    }
+
+    fn occupied(&self) -> usize {
+        self.reports.iter().map(|(_, bytes)| bytes).sum::<usize>() + self.partial
+    }
+}
+
+pub fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now {
+        thread::sleep(deadline - now);
+    }
+}
@@ -0,0 +1,315 @@
+use super::{
+    parser::{self, Command, Node},
+    Location, Script,
+};
+use crate::{
+    action::{
+        key::{try_create_message_with, Encoding},
+        KeyCode, MouseAction, ScrollDirection, ScrollMagnitude, Sequence,
+    },
+    error::{Error, Result},
+    journal::Kind,
+    signal, Emulator,
+};
+use std::{collections::HashMap, time::Duration};
+
+// Commands a script may unroll to, counting every repeat iteration
+pub const MAX_STEPS: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Write(Kind, Vec<u8>),
+    Hold(Vec<KeyCode>, Duration),
+    Wait(Duration),
+    // Journal marker, one per section call
+    Mark(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program(Vec<Instruction>);
+
+impl Program {
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.0
+    }
+
+    // Held keys and buttons are released if the program fails partway
+    pub fn run(&self, emulator: &mut Emulator) -> Result<()> {
+        match self.execute(emulator) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                if !signal::interrupted() {
+                    let _ = emulator.release_all();
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    fn execute(&self, emulator: &mut Emulator) -> Result<()> {
+        for instruction in &self.0 {
+            match instruction {
+                Instruction::Write(kind, packet) => {
+                    emulator.write_as(*kind, packet.clone(), Duration::ZERO)?
+                }
+                Instruction::Hold(keys, duration) => emulator.hold(keys, *duration)?,
+                Instruction::Wait(duration) => emulator.wait(*duration),
+                Instruction::Mark(name) => emulator.mark(name),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) fn compile(
+    script: &Script,
+    section: Option<&str>,
+    encoding: Encoding,
+) -> Result<Program> {
+    let mut compiler = Compiler {
+        script,
+        encoding,
+        variables: script.variables.clone(),
+        calls: vec![],
+        program: vec![],
+        steps: 0,
+    };
+
+    match section {
+        None => compiler.nodes(&script.nodes)?,
+        Some(name) => {
+            for node in &script.nodes {
+                if let Command::Set(..) = node.command {
+                    compiler.node(node)?;
+                }
+            }
+
+            let Some(body) = script.sections.get(name) else {
+                return Err(Error::Config(format!("no section named {name:?}")));
+            };
+            compiler.program.push(Instruction::Mark(name.to_owned()));
+            compiler.calls.push(name.to_owned());
+            compiler.nodes(body)?;
+        }
+    }
+
+    Ok(Program(compiler.program))
+}
+
+struct Compiler<'a> {
+    script: &'a Script,
+    encoding: Encoding,
+    variables: HashMap<String, String>,
+    calls: Vec<String>,
+    program: Vec<Instruction>,
+    steps: usize,
+}
+
+impl Compiler<'_> {
+    fn nodes(&mut self, nodes: &[Node]) -> Result<()> {
+        nodes.iter().try_for_each(|node| self.node(node))
+    }
+
+    fn node(&mut self, node: &Node) -> Result<()> {
+        let location = &node.location;
+        let error = |message: String| location.error(message);
+        self.step(location)?;
+
+        let instruction = match &node.command {
+            Command::Type(text) => {
+                let text = parser::unquote(&self.expand(text, location)?);
+                let packet = try_create_message_with(&text, self.encoding)
+                    .map_err(|err| error(err.to_string()))?;
+
+                Instruction::Write(Kind::Message, packet)
+            }
+            Command::Key(sequence) => {
+                let packet = Sequence::parse(&self.expand(sequence, location)?)
+                    .and_then(|sequence| sequence.packet(self.encoding))
+                    .map_err(|err| error(err.to_string()))?;
+
+                Instruction::Write(Kind::Command, packet)
+            }
+            Command::Hold(arguments) => {
+                let arguments = self.expand(arguments, location)?;
+                let Some((keys, duration)) = arguments.rsplit_once(char::is_whitespace) else {
+                    return Err(error("expected `hold <keys> <duration>`".to_owned()));
+                };
+                let keys = keys
+                    .split(|char: char| char == '+' || char.is_whitespace())
+                    .filter(|name| !name.is_empty())
+                    .map(str::parse)
+                    .collect::<std::result::Result<Vec<KeyCode>, String>>()
+                    .map_err(error)?;
+
+                Instruction::Hold(keys, parse_duration(duration).map_err(error)?)
+            }
+            Command::Move(position) => {
+                let position = self.expand(position, location)?;
+                let coordinates = position
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<std::result::Result<Vec<u16>, _>>();
+                let Ok([x, y]) = coordinates.as_deref() else {
+                    return Err(error(format!("expected `move <x> <y>`, got {position:?}")));
+                };
+
+                Instruction::Write(Kind::Mouse, MouseAction::Move(*x, *y).as_packet())
+            }
+            Command::Click(button) => {
+                let action = match self.expand(button, location)?.to_ascii_lowercase().as_str() {
+                    "" | "left" => MouseAction::LeftClick,
+                    "middle" => MouseAction::MiddleClick,
+                    "right" => MouseAction::RightClick,
+                    button => return Err(error(format!("unknown button {button:?}"))),
+                };
+
+                Instruction::Write(Kind::Mouse, action.as_packet())
+            }
+            Command::Scroll(arguments) => {
+                let arguments = self.expand(arguments, location)?;
+                let mut words = arguments.split_whitespace();
+                let direction = match words.next().map(str::to_ascii_lowercase).as_deref() {
+                    Some("up") => ScrollDirection::Up,
+                    Some("down") => ScrollDirection::Down,
+                    _ => return Err(error(format!("expected up or down, got {arguments:?}"))),
+                };
+                let magnitude = match words.next() {
+                    None => Some(ScrollMagnitude::One),
+                    Some(word) => word
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|magnitude| *magnitude <= 7)
+                        .and_then(|magnitude| ScrollMagnitude::try_from(magnitude << 4).ok()),
+                };
+                let (Some(magnitude), None) = (magnitude, words.next()) else {
+                    return Err(error("expected `scroll <up|down> [0-7]`".to_owned()));
+                };
+
+                Instruction::Write(
+                    Kind::Mouse,
+                    MouseAction::Scroll(direction, magnitude).as_packet(),
+                )
+            }
+            Command::Wait(duration) => {
+                Instruction::Wait(parse_duration(&self.expand(duration, location)?).map_err(error)?)
+            }
+            Command::Set(name, value) => {
+                if !self.script.variables.contains_key(name) {
+                    let value = self.expand(value, location)?;
+                    self.variables.insert(name.clone(), value);
+                }
+
+                return Ok(());
+            }
+            Command::Repeat(count, body) => {
+                let count = self.expand(count, location)?;
+                let Ok(count) = count.parse::<usize>() else {
+                    return Err(error(format!("invalid repeat count {count:?}")));
+                };
+
+                for _ in 0..count {
+                    self.step(location)?;
+                    self.nodes(body)?;
+                }
+
+                return Ok(());
+            }
+            Command::Call(name) => {
+                let name = self.expand(name, location)?;
+                return self.call(&name, location);
+            }
+        };
+
+        self.program.push(instruction);
+
+        Ok(())
+    }
+
+    fn step(&mut self, location: &Location) -> Result<()> {
+        self.steps += 1;
+        match self.steps > MAX_STEPS {
+            true => Err(location.error(format!("script unrolls to over {MAX_STEPS} steps"))),
+            false => Ok(()),
+        }
+    }
+
+    fn call(&mut self, name: &str, location: &Location) -> Result<()> {
+        let Some(body) = self.script.sections.get(name) else {
+            return Err(location.error(format!("no section named {name:?}")));
+        };
+        if self.calls.iter().any(|call| call == name) {
+            return Err(location.error(format!("section {name:?} calls itself")));
+        }
+
+        self.program.push(Instruction::Mark(name.to_owned()));
+        self.calls.push(name.to_owned());
+        self.nodes(body)?;
+        self.calls.pop();
+
+        Ok(())
+    }
+
+    fn expand(&self, text: &str, location: &Location) -> Result<String> {
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(index) = rest.find('$') {
+            expanded.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+
+            let name = if rest.starts_with('$') {
+                expanded.push('$');
+                rest = &rest[1..];
+                continue;
+            } else if let Some(braced) = rest.strip_prefix('{') {
+                let Some(close) = braced.find('}') else {
+                    return Err(location.error("unclosed ${"));
+                };
+                rest = &braced[close + 1..];
+                &braced[..close]
+            } else {
+                let end = rest
+                    .find(|char: char| !char.is_ascii_alphanumeric() && char != '_')
+                    .unwrap_or(rest.len());
+                let name = &rest[..end];
+                if !parser::is_name(name) {
+                    expanded.push('$');
+                    continue;
+                }
+                rest = &rest[end..];
+                name
+            };
+
+            match self.variables.get(name) {
+                Some(value) => expanded.push_str(value),
+                None => return Err(location.error(format!("undefined variable {name:?}"))),
+            }
+        }
+        expanded.push_str(rest);
+
+        Ok(expanded)
+    }
+}
+
+fn parse_duration(text: &str) -> std::result::Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|char: char| !char.is_ascii_digit() && char != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+
+    let scale = match unit {
+        "" | "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        _ => return Err(format!("invalid duration {text:?}")),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .and_then(|number| Duration::try_from_secs_f64(number * scale).ok())
+        .ok_or_else(|| format!("invalid duration {text:?}"))
+}
@@ -0,0 +1,453 @@
+// Interpreted macro scripts
+//
+// A script is a UTF-8 text file with one command per line. Blank lines and
+// lines starting with `#` are ignored.
+//
+//     type <text>              types text, keep spaces or add \n and \t
+//                              escapes by quoting it, `type "a\tb\n"`
+//     key <sequence>           keys mixed with text, see `action::Sequence`
+//     hold <keys> <duration>   `hold ctrl+shift 2s`
+//     move <x> <y>
+//     click [left|middle|right]
+//     scroll <up|down> [0-7]
+//     wait <duration>          `250ms`, `2s`, `1.5s`, `1m` or milliseconds
+//     set <name> = <value>
+//     repeat <count>
+//         ...
+//     end
+//     section <name>
+//         ...
+//     end
+//     call <name>
+//     include <path>
+//
+// Arguments can use variables as `$name` or `${name}`, `$$` is a literal `$`.
+// Variables are global and a `set` only affects the lines after it, values
+// given through `Script::set` take precedence over the script's own.
+//
+// `include` pulls another script in at that line, relative to the including
+// file. A file already included elsewhere is skipped. Sections can only be defined at the top level of a file, each call
+// is recorded as a marker in the emulator's journal.
+//
+// The whole script is compiled to packets before anything is sent, so a typo
+// or untypeable character fails the run up front rather than halfway.
+// Repeats and calls are unrolled, at most `MAX_STEPS` commands in total.
+
+mod compiler;
+mod parser;
+
+pub use compiler::{Instruction, Program, MAX_STEPS};
+
+use crate::{
+    action::key::Encoding,
+    error::{Error, Result},
+    Emulator,
+};
+use parser::{Includes, Node};
+use std::{collections::HashMap, fmt, path::Path};
+
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    nodes: Vec<Node>,
+    sections: HashMap<String, Vec<Node>>,
+    variables: HashMap<String, String>,
+}
+
+impl Script {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut script = Self::default();
+        script.nodes = parser::parse_file(
+            path.as_ref(),
+            &mut script.sections,
+            &mut Includes::default(),
+        )?;
+
+        Ok(script)
+    }
+
+    // Includes are resolved against the working directory
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut script = Self::default();
+        script.nodes = parser::parse_source(
+            source,
+            "<script>",
+            Path::new("."),
+            &mut script.sections,
+            &mut Includes::default(),
+        )?;
+
+        Ok(script)
+    }
+
+    pub fn set(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item = &str> {
+        self.sections.keys().map(String::as_str)
+    }
+
+    pub fn compile(&self, encoding: impl Into<Encoding>) -> Result<Program> {
+        compiler::compile(self, None, encoding.into())
+    }
+
+    // Only the section `name`, after the `set` lines at the script's top level
+    pub fn compile_section(&self, name: &str, encoding: impl Into<Encoding>) -> Result<Program> {
+        compiler::compile(self, Some(name), encoding.into())
+    }
+
+    // Compiled for the emulator's layout and Unicode input
+    pub fn run(&self, emulator: &mut Emulator) -> Result<()> {
+        self.compile(emulator.encoding())?.run(emulator)
+    }
+
+    pub fn run_section(&self, name: &str, emulator: &mut Emulator) -> Result<()> {
+        self.compile_section(name, emulator.encoding())?
+            .run(emulator)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+}
+
+impl Location {
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::Script {
+            location: self.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{key, HostLayout, KeyCode, MouseAction, ScrollDirection, ScrollMagnitude},
+        journal::Kind,
+    };
+    use std::{fs, path::PathBuf, process, time::Duration};
+
+    const US: HostLayout = HostLayout::UsQwerty;
+
+    fn compile(source: &str) -> Result<Vec<Instruction>> {
+        Ok(Script::parse(source)?.compile(US)?.instructions().to_vec())
+    }
+
+    fn error(source: &str) -> String {
+        match compile(source) {
+            Err(err) => err.to_string(),
+            Ok(program) => panic!("{source:?} compiled to {program:?}"),
+        }
+    }
+
+    fn typed(text: &str) -> Instruction {
+        Instruction::Write(
+            Kind::Message,
+            key::try_create_message_with(text, US).unwrap(),
+        )
+    }
+
+    fn command(keys: Vec<KeyCode>) -> Instruction {
+        Instruction::Write(Kind::Command, key::create_command(keys))
+    }
+
+    fn mouse(action: MouseAction) -> Instruction {
+        Instruction::Write(Kind::Mouse, action.as_packet())
+    }
+
+    // A fresh directory under the system temp dir for include tests
+    fn directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("hagstrom-script-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn commands_compile_to_packets() {
+        let source = "
+            # comment
+            type hello world
+            key ctrl+c
+            hold shift+a 250ms
+            move 10 20
+            click
+            click right
+            scroll down 3
+            wait 1.5s
+        ";
+
+        assert_eq!(
+            compile(source).unwrap(),
+            [
+                typed("hello world"),
+                command(vec![KeyCode::Control, KeyCode::C]),
+                Instruction::Hold(vec![KeyCode::Shift, KeyCode::A], Duration::from_millis(250)),
+                mouse(MouseAction::Move(10, 20)),
+                mouse(MouseAction::LeftClick),
+                mouse(MouseAction::RightClick),
+                mouse(MouseAction::Scroll(
+                    ScrollDirection::Down,
+                    ScrollMagnitude::Three
+                )),
+                Instruction::Wait(Duration::from_millis(1500)),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_text_keeps_spaces_and_escapes() {
+        assert_eq!(
+            compile(r#"type "  a\tb\n\"c\"""#).unwrap(),
+            [typed("  a\tb\n\"c\"")]
+        );
+    }
+
+    #[test]
+    fn durations() {
+        for (text, millis) in [
+            ("250", 250),
+            ("250ms", 250),
+            ("2s", 2000),
+            ("0.5s", 500),
+            ("1m", 60_000),
+        ] {
+            assert_eq!(
+                compile(&format!("wait {text}")).unwrap(),
+                [Instruction::Wait(Duration::from_millis(millis))],
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn variables_expand_and_can_be_overridden() {
+        let source = "
+            set who = Al
+            set greeting = hi ${who}
+            type $greeting$$
+        ";
+
+        assert_eq!(compile(source).unwrap(), [typed("hi Al$")]);
+        assert_eq!(
+            Script::parse(source)
+                .unwrap()
+                .set("who", "Bo")
+                .compile(US)
+                .unwrap()
+                .instructions(),
+            [typed("hi Bo$")]
+        );
+    }
+
+    #[test]
+    fn repeat_unrolls_nested_blocks() {
+        let source = "
+            set n = 2
+            repeat $n
+                type a
+                repeat 2
+                    type b
+                end
+            end
+            repeat 0
+                type never
+            end
+        ";
+
+        assert_eq!(
+            compile(source).unwrap(),
+            [
+                typed("a"),
+                typed("b"),
+                typed("b"),
+                typed("a"),
+                typed("b"),
+                typed("b")
+            ]
+        );
+    }
+
+    #[test]
+    fn sections_are_marked_when_called() {
+        let source = "
+            section greet
+                type hi
+            end
+            call greet
+            call greet
+        ";
+        let mark = || Instruction::Mark("greet".to_owned());
+
+        assert_eq!(
+            compile(source).unwrap(),
+            [mark(), typed("hi"), mark(), typed("hi")]
+        );
+
+        let script = Script::parse(source).unwrap();
+        assert_eq!(script.sections().collect::<Vec<_>>(), ["greet"]);
+        assert_eq!(
+            script.compile_section("greet", US).unwrap().instructions(),
+            [mark(), typed("hi")]
+        );
+        assert!(script.compile_section("missing", US).is_err());
+    }
+
+    #[test]
+    fn includes_resolve_relative_to_the_including_file() {
+        let directory = directory("include");
+        fs::create_dir_all(directory.join("lib")).unwrap();
+        fs::write(
+            directory.join("main.hag"),
+            "include lib/common.hag\ncall greet\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("lib/common.hag"),
+            "set who = Al\nsection greet\n    type $who\nend\n",
+        )
+        .unwrap();
+
+        let program = Script::open(directory.join("main.hag"))
+            .unwrap()
+            .compile(US)
+            .unwrap();
+        assert_eq!(
+            program.instructions(),
+            [Instruction::Mark("greet".to_owned()), typed("Al")]
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn shared_includes_are_pulled_in_once() {
+        let directory = directory("diamond");
+        fs::write(directory.join("main.hag"), "include a.hag\ninclude b.hag\n").unwrap();
+        fs::write(directory.join("a.hag"), "include common.hag\ntype a\n").unwrap();
+        fs::write(directory.join("b.hag"), "include common.hag\ncall greet\n").unwrap();
+        fs::write(
+            directory.join("common.hag"),
+            "type c\nsection greet\n    type hi\nend\n",
+        )
+        .unwrap();
+
+        let program = Script::open(directory.join("main.hag"))
+            .unwrap()
+            .compile(US)
+            .unwrap();
+        assert_eq!(
+            program.instructions(),
+            [
+                typed("c"),
+                typed("a"),
+                Instruction::Mark("greet".to_owned()),
+                typed("hi")
+            ]
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn unrolling_is_capped() {
+        assert_eq!(
+            error("type a\nrepeat 100000\n  repeat 100000\n    type a\n  end\nend"),
+            format!("<script>:3: script unrolls to over {MAX_STEPS} steps")
+        );
+        assert_eq!(
+            error("repeat 99999999999\nend"),
+            format!("<script>:1: script unrolls to over {MAX_STEPS} steps")
+        );
+        assert_eq!(compile("repeat 1000\n  type a\nend").unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let directory = directory("cycle");
+        fs::write(directory.join("a.hag"), "include b.hag\n").unwrap();
+        fs::write(directory.join("b.hag"), "type x\ninclude \"a.hag\"\n").unwrap();
+
+        let message = Script::open(directory.join("a.hag"))
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("b.hag:2: "), "{message}");
+        assert!(message.ends_with("a.hag includes itself"), "{message}");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_line() {
+        for (source, expected) in [
+            ("type a\n\nfrob x", "<script>:3: unknown command `frob`"),
+            ("repeat 2\n  type a", "<script>:1: `repeat` without `end`"),
+            ("type a\nend", "<script>:2: `end` without a block"),
+            ("type", "<script>:1: `type` needs text"),
+            ("set x 1", "<script>:1: expected `set <name> = <value>`"),
+            ("set 1x = 1", "<script>:1: invalid variable name \"1x\""),
+            (
+                "repeat 1\n  section s\n  end\nend",
+                "<script>:2: sections can only be defined at the top level",
+            ),
+            (
+                "section s\nend\nsection s\nend",
+                "<script>:3: section \"s\" is already defined",
+            ),
+        ] {
+            assert_eq!(error(source), expected, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn compile_errors_point_at_the_line() {
+        for (source, expected) in [
+            ("wait 5q", "<script>:1: invalid duration \"5q\""),
+            (
+                "type a\nwait 99999999999999999999999m",
+                "<script>:2: invalid duration \"99999999999999999999999m\"",
+            ),
+            ("type $nope", "<script>:1: undefined variable \"nope\""),
+            ("type ${open", "<script>:1: unclosed ${"),
+            ("call x", "<script>:1: no section named \"x\""),
+            (
+                "section s\n  call s\nend\ncall s",
+                "<script>:2: section \"s\" calls itself",
+            ),
+            ("move 1", "<script>:1: expected `move <x> <y>`, got \"1\""),
+            ("click up", "<script>:1: unknown button \"up\""),
+            (
+                "scroll down 8",
+                "<script>:1: expected `scroll <up|down> [0-7]`",
+            ),
+            ("repeat x\nend", "<script>:1: invalid repeat count \"x\""),
+            (
+                "hold shift",
+                "<script>:1: expected `hold <keys> <duration>`",
+            ),
+            ("hold foo 1s", "<script>:1: unknown key \"foo\""),
+            (
+                "key {Foo}",
+                "<script>:1: Invalid key sequence at 1..4: unknown key \"Foo\"",
+            ),
+            (
+                "type h\u{e9}",
+                "<script>:1: Can't type '\u{e9}' (U+00E9) at char 1, byte 1",
+            ),
+        ] {
+            assert_eq!(error(source), expected, "{source:?}");
+        }
+    }
+}
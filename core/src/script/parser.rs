@@ -0,0 +1,225 @@
+use super::Location;
+use crate::error::{Error, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone)]
+pub(super) struct Node {
+    pub location: Location,
+    pub command: Command,
+}
+
+// Arguments are kept as written, variables are expanded when compiling
+#[derive(Debug, Clone)]
+pub(super) enum Command {
+    Type(String),
+    Key(String),
+    Hold(String),
+    Move(String),
+    Click(String),
+    Scroll(String),
+    Wait(String),
+    Set(String, String),
+    Repeat(String, Vec<Node>),
+    Call(String),
+}
+
+// Files being parsed, innermost last, and every file parsed so far. A file
+// is only pulled in once, so two includes of the same library are fine but a
+// file including itself is an error.
+#[derive(Debug, Default)]
+pub(super) struct Includes {
+    stack: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+}
+
+pub(super) fn parse_file(
+    path: &Path,
+    sections: &mut HashMap<String, Vec<Node>>,
+    includes: &mut Includes,
+) -> Result<Vec<Node>> {
+    let canonical = fs::canonicalize(path)?;
+    let source = fs::read_to_string(&canonical)?;
+    let directory = canonical.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+    includes.seen.insert(canonical.clone());
+    includes.stack.push(canonical);
+    let nodes = parse_source(
+        &source,
+        &path.display().to_string(),
+        &directory,
+        sections,
+        includes,
+    );
+    includes.stack.pop();
+
+    nodes
+}
+
+pub(super) fn parse_source(
+    source: &str,
+    file: &str,
+    directory: &Path,
+    sections: &mut HashMap<String, Vec<Node>>,
+    includes: &mut Includes,
+) -> Result<Vec<Node>> {
+    let mut parser = Parser {
+        lines: source.lines().enumerate(),
+        file,
+        directory,
+        sections,
+        includes,
+    };
+
+    match parser.block(None)? {
+        (nodes, None) => Ok(nodes),
+        (_, Some(location)) => Err(location.error("`end` without a block")),
+    }
+}
+
+struct Parser<'a, I> {
+    lines: I,
+    file: &'a str,
+    directory: &'a Path,
+    sections: &'a mut HashMap<String, Vec<Node>>,
+    includes: &'a mut Includes,
+}
+
+impl<'a, I: Iterator<Item = (usize, &'a str)>> Parser<'a, I> {
+    // Nodes up to the end of the file or the `end` closing the block opened
+    // at `opened`, along with where that `end` is
+    fn block(&mut self, opened: Option<&Location>) -> Result<(Vec<Node>, Option<Location>)> {
+        let mut nodes = vec![];
+
+        while let Some((index, line)) = self.lines.next() {
+            let location = Location {
+                file: self.file.to_owned(),
+                line: index + 1,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (word, rest) = match line.split_once(char::is_whitespace) {
+                Some((word, rest)) => (word, rest.trim()),
+                None => (line, ""),
+            };
+            let required = |what: &str| match rest.is_empty() {
+                true => Err(location.error(format!("`{word}` needs {what}"))),
+                false => Ok(rest.to_owned()),
+            };
+
+            let command = match word {
+                "type" => Command::Type(required("text")?),
+                "key" => Command::Key(required("a key sequence")?),
+                "hold" => Command::Hold(required("keys and a duration")?),
+                "move" => Command::Move(required("a position")?),
+                "click" => Command::Click(rest.to_owned()),
+                "scroll" => Command::Scroll(required("a direction")?),
+                "wait" => Command::Wait(required("a duration")?),
+                "set" => {
+                    let Some((name, value)) = rest.split_once('=') else {
+                        return Err(location.error("expected `set <name> = <value>`"));
+                    };
+                    let name = name.trim();
+                    if !is_name(name) {
+                        return Err(location.error(format!("invalid variable name {name:?}")));
+                    }
+
+                    Command::Set(name.to_owned(), value.trim().to_owned())
+                }
+                "repeat" => {
+                    let count = required("a count")?;
+                    let body = self.body(&location, "repeat")?;
+
+                    Command::Repeat(count, body)
+                }
+                "section" => {
+                    if opened.is_some() {
+                        return Err(location.error("sections can only be defined at the top level"));
+                    }
+                    if !is_name(rest) {
+                        return Err(location.error(format!("invalid section name {rest:?}")));
+                    }
+                    if self.sections.contains_key(rest) {
+                        return Err(location.error(format!("section {rest:?} is already defined")));
+                    }
+
+                    let body = self.body(&location, "section")?;
+                    self.sections.insert(rest.to_owned(), body);
+                    continue;
+                }
+                "call" => Command::Call(required("a section name")?),
+                "include" => {
+                    let path = self.directory.join(unquote(&required("a path")?));
+                    nodes.extend(self.include(&path, &location)?);
+                    continue;
+                }
+                "end" => return Ok((nodes, Some(location))),
+                _ => return Err(location.error(format!("unknown command `{word}`"))),
+            };
+
+            nodes.push(Node { location, command });
+        }
+
+        Ok((nodes, None))
+    }
+
+    fn body(&mut self, location: &Location, kind: &str) -> Result<Vec<Node>> {
+        match self.block(Some(location))? {
+            (nodes, Some(_)) => Ok(nodes),
+            (_, None) => Err(location.error(format!("`{kind}` without `end`"))),
+        }
+    }
+
+    fn include(&mut self, path: &Path, location: &Location) -> Result<Vec<Node>> {
+        let canonical = fs::canonicalize(path)
+            .map_err(|err| location.error(format!("can't include {}: {err}", path.display())))?;
+        if self.includes.stack.contains(&canonical) {
+            return Err(location.error(format!("{} includes itself", path.display())));
+        }
+        if self.includes.seen.contains(&canonical) {
+            return Ok(vec![]);
+        }
+
+        parse_file(path, self.sections, self.includes).map_err(|err| match err {
+            Error::Script { .. } => err,
+            err => location.error(format!("can't include {}: {err}", path.display())),
+        })
+    }
+}
+
+pub(super) fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(char) if char.is_ascii_alphabetic() || char == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+pub(super) fn unquote(text: &str) -> String {
+    let Some(quoted) = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+    else {
+        return text.to_owned();
+    };
+
+    let mut text = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(char) => text.push(char),
+                None => text.push('\\'),
+            },
+            char => text.push(char),
+        }
+    }
+
+    text
+}
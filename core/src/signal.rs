@@ -0,0 +1,69 @@
+// Opt-in SIGINT/SIGTERM handling. Once installed, an interrupt stops every
+// emulator at its next chunk boundary, releases whatever each one is holding,
+// flushes the key buffer and only then exits the process.
+
+use crate::{
+    emulator::link::{self, SharedLink},
+    error::Result,
+};
+use lazy_static::lazy_static;
+use std::{
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+const EXIT_CODE: i32 = 130;
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref LINKS: Mutex<Vec<Weak<Mutex<link::Link>>>> = Mutex::new(vec![]);
+}
+
+pub fn install() -> Result<()> {
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    ctrlc::set_handler(handle).map_err(|err| {
+        INSTALLED.store(false, Ordering::SeqCst);
+        err.into()
+    })
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+pub(crate) fn register(link: &SharedLink) {
+    let mut links = match LINKS.lock() {
+        Ok(links) => links,
+        Err(err) => err.into_inner(),
+    };
+    links.retain(|link| link.strong_count() > 0);
+    links.push(Arc::downgrade(link));
+}
+
+// Runs on ctrlc's handler thread rather than in signal context, so locking is
+// fine here. Each link lock is only held for a single chunk by writers, and
+// `Link::write` refuses to send once the flag is set, so a writer that was
+// waiting on the lock can't press anything after the release.
+fn handle() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    let links = match LINKS.lock() {
+        Ok(links) => links,
+        Err(err) => err.into_inner(),
+    };
+    links.iter().filter_map(Weak::upgrade).for_each(|link| {
+        if let Err(err) = link::lock(&link).release_all() {
+            eprintln!("Failed to release emulator: {err}");
+        }
+    });
+
+    process::exit(EXIT_CODE);
+}
@@ -0,0 +1,90 @@
+use super::{Event, Simulator};
+use crate::{
+    action::{KeyCode, MouseButton},
+    transport::Transport,
+};
+use std::{
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+#[derive(Default)]
+struct State {
+    simulator: Simulator,
+    events: Vec<Event>,
+}
+
+// A simulated device usable as an emulator transport. Clones share state, so
+// a handle can be kept to inspect what the emulator sent.
+#[derive(Clone, Default)]
+pub struct Device(Arc<Mutex<State>>);
+
+impl Device {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.state().events.clone()
+    }
+
+    pub fn take_events(&self) -> Vec<Event> {
+        std::mem::take(&mut self.state().events)
+    }
+
+    pub fn held_keys(&self) -> Vec<KeyCode> {
+        self.state().simulator.held_keys().iter().copied().collect()
+    }
+
+    pub fn held_buttons(&self) -> Vec<MouseButton> {
+        self.state()
+            .simulator
+            .held_buttons()
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    pub fn cursor(&self) -> (i32, i32) {
+        self.state().simulator.cursor()
+    }
+
+    pub fn feed(&self, bytes: &[u8]) -> Vec<Event> {
+        let mut state = self.state();
+        let events = state.simulator.feed(bytes);
+        state.events.extend(events.iter().copied());
+
+        events
+    }
+
+    pub(crate) fn record(&self, event: Event) -> Event {
+        self.state().events.push(event);
+
+        event
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        match self.0.lock() {
+            Ok(state) => state,
+            Err(err) => err.into_inner(),
+        }
+    }
+}
+
+impl io::Write for Device {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Device {
+    fn name(&self) -> Option<String> {
+        Some("simulator".to_owned())
+    }
+}
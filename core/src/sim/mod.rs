@@ -0,0 +1,228 @@
+// Decodes the byte stream an `Emulator` produces back into the keyboard and
+// mouse events the device would emit, tracking held keys, buttons and the
+// cursor along the way
+
+mod device;
+#[cfg(unix)]
+mod virtual_device;
+
+pub use device::Device;
+#[cfg(unix)]
+pub use virtual_device::{BufferLimit, VirtualDevice};
+
+use crate::{
+    action::{
+        KeyCode, MouseAction, MouseButton, ScrollDirection, ScrollMagnitude, CODE_MAP, KEY_MAP,
+    },
+    FLUSH,
+};
+use std::{collections::HashSet, fmt};
+
+const MOUSE_FRAME: u8 = 0x00;
+const MOUSE_FRAME_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    KeyPress(KeyCode),
+    KeyRelease(KeyCode),
+    MouseMove(i16, i16),
+    ButtonPress(MouseButton),
+    ButtonRelease(MouseButton),
+    Scroll(ScrollDirection, ScrollMagnitude),
+    Flush,
+    Unknown(u8),
+    // Arrived while the device's input buffer was full
+    Dropped(u8),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyPress(key) => write!(f, "press {key:?}"),
+            Self::KeyRelease(key) => write!(f, "release {key:?}"),
+            Self::MouseMove(x, y) => write!(f, "mouse move {x},{y}"),
+            Self::ButtonPress(button) => write!(f, "mouse press {button:?}"),
+            Self::ButtonRelease(button) => write!(f, "mouse release {button:?}"),
+            Self::Scroll(direction, magnitude) => {
+                write!(f, "mouse scroll {direction:?} {magnitude:?}")
+            }
+            Self::Flush => write!(f, "flush"),
+            Self::Unknown(byte) => write!(f, "unknown 0x{byte:02x}"),
+            Self::Dropped(byte) => write!(f, "dropped 0x{byte:02x}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Simulator {
+    frame: Vec<u8>,
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<MouseButton>,
+    cursor: (i32, i32),
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let mut events = vec![];
+        bytes
+            .iter()
+            .for_each(|byte| self.feed_byte(*byte, &mut events));
+
+        events
+    }
+
+    pub fn held_keys(&self) -> &HashSet<KeyCode> {
+        &self.keys
+    }
+
+    pub fn held_buttons(&self) -> &HashSet<MouseButton> {
+        &self.buttons
+    }
+
+    // Move packets carry relative deltas, this is their running sum
+    pub fn cursor(&self) -> (i32, i32) {
+        self.cursor
+    }
+
+    // Whether the stream currently ends part way through a mouse frame
+    pub fn in_frame(&self) -> bool {
+        !self.frame.is_empty()
+    }
+
+    // Bytes that bring the device back to idle: the rest of any unfinished
+    // mouse frame, a release for every held key and a frame with no buttons
+    pub fn release_packet(&self) -> Vec<u8> {
+        let mut packet = self.frame_padding();
+
+        let mut keys: Vec<KeyCode> = self.keys.iter().copied().collect();
+        keys.sort_by_key(|key| (key.is_modifier(), *key as u8));
+        packet.extend(
+            keys.iter()
+                .filter_map(|key| KEY_MAP.get(key))
+                .map(|(_, release)| release),
+        );
+
+        if !self.buttons.is_empty() {
+            packet.extend(MouseAction::Move(0, 0).as_packet());
+        }
+
+        packet
+    }
+
+    // Completes an unfinished mouse frame with no movement and no buttons
+    pub fn frame_padding(&self) -> Vec<u8> {
+        match self.frame.len() {
+            0 => vec![],
+            len => {
+                let mut padding = vec![0; MOUSE_FRAME_LEN - len - 1];
+                padding.push(0x08);
+                padding
+            }
+        }
+    }
+
+    // Nothing held and no mouse frame in progress
+    pub fn is_idle(&self) -> bool {
+        self.keys.is_empty() && self.buttons.is_empty() && !self.in_frame()
+    }
+
+    // Offsets into `packet` at which the device would be back in the state it
+    // started in, always including the start of the packet. From an idle
+    // start these are the points where it's idle again.
+    pub fn boundaries(&self, packet: &[u8]) -> Vec<usize> {
+        let mut probe = self.clone();
+        let mut boundaries = vec![0];
+        packet.iter().enumerate().for_each(|(i, byte)| {
+            probe.feed(&[*byte]);
+            if probe.keys == self.keys && probe.buttons == self.buttons && !probe.in_frame() {
+                boundaries.push(i + 1);
+            }
+        });
+
+        boundaries
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn feed_byte(&mut self, byte: u8, events: &mut Vec<Event>) {
+        if self.in_frame() || byte == MOUSE_FRAME {
+            self.frame.push(byte);
+            if self.frame.len() == MOUSE_FRAME_LEN {
+                let frame = std::mem::take(&mut self.frame);
+                self.decode_frame(&frame, events);
+            }
+
+            return;
+        }
+
+        if byte == FLUSH {
+            events.push(Event::Flush);
+
+            return;
+        }
+
+        match CODE_MAP.get(&byte) {
+            Some((key, true)) => {
+                self.keys.insert(*key);
+                events.push(Event::KeyPress(*key));
+            }
+            Some((key, false)) => {
+                self.keys.remove(key);
+                events.push(Event::KeyRelease(*key));
+            }
+            None => events.push(Event::Unknown(byte)),
+        }
+    }
+
+    fn decode_frame(&mut self, frame: &[u8], events: &mut Vec<Event>) {
+        let dx = i16::from_be_bytes([frame[1], frame[2]]);
+        let dy = i16::from_be_bytes([frame[3], frame[4]]);
+        let scroll = frame[5];
+
+        if dx != 0 || dy != 0 {
+            self.cursor.0 += dx as i32;
+            self.cursor.1 += dy as i32;
+            events.push(Event::MouseMove(dx, dy));
+        }
+
+        [
+            (MouseButton::Left, 0b001),
+            (MouseButton::Right, 0b010),
+            (MouseButton::Middle, 0b100),
+        ]
+        .into_iter()
+        .for_each(|(button, mask)| {
+            let pressed = scroll & mask != 0;
+            match (pressed, self.buttons.contains(&button)) {
+                (true, false) => {
+                    self.buttons.insert(button);
+                    events.push(Event::ButtonPress(button));
+                }
+                (false, true) => {
+                    self.buttons.remove(&button);
+                    events.push(Event::ButtonRelease(button));
+                }
+                _ => {}
+            }
+        });
+
+        if let (Ok(direction), Ok(magnitude)) = (
+            ScrollDirection::try_from(scroll & 0x80),
+            ScrollMagnitude::try_from(scroll & 0x70),
+        ) {
+            if magnitude != ScrollMagnitude::Zero {
+                events.push(Event::Scroll(direction, magnitude));
+            }
+        }
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Vec<Event> {
+    Simulator::new().feed(bytes)
+}
@@ -0,0 +1,135 @@
+use super::{Device, Event};
+use crate::error::Result;
+use serialport::{SerialPort, TTYPort};
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+type Listener = Box<dyn FnMut(&Event) + Send>;
+
+// Models the device's input buffer as a leaky bucket, bytes arriving while it
+// is full are dropped just like the hardware would
+#[derive(Debug, Clone, Copy)]
+pub struct BufferLimit {
+    pub capacity: usize,
+    pub drain_rate: u32,
+}
+
+impl BufferLimit {
+    fn admit(&self, level: &mut f64, elapsed: Duration) -> bool {
+        *level = (*level - elapsed.as_secs_f64() * self.drain_rate as f64).max(0.0);
+        if *level + 1.0 > self.capacity as f64 {
+            return false;
+        }
+        *level += 1.0;
+
+        true
+    }
+}
+
+// A simulated KM emulator listening on the slave side of a pseudo terminal,
+// so `Emulator::new(path)` reaches it through the regular serial port code
+pub struct VirtualDevice {
+    path: String,
+    device: Device,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl VirtualDevice {
+    pub fn spawn(limit: Option<BufferLimit>, listener: Option<Listener>) -> Result<Self> {
+        let (mut master, slave) = TTYPort::pair()?;
+        let Some(path) = slave.name() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "pty has no slave path").into());
+        };
+        master.set_timeout(Duration::from_millis(50))?;
+
+        let device = Device::new();
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = {
+            let device = device.clone();
+            let running = running.clone();
+
+            thread::spawn(move || listen(master, slave, device, running, limit, listener))
+        };
+
+        Ok(Self {
+            path,
+            device,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+}
+
+impl Drop for VirtualDevice {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// The slave is held open for the lifetime of the listener, otherwise reads on
+// the master fail whenever no client is attached
+fn listen(
+    mut master: TTYPort,
+    _slave: TTYPort,
+    device: Device,
+    running: Arc<AtomicBool>,
+    limit: Option<BufferLimit>,
+    mut listener: Option<Listener>,
+) {
+    let mut buffer = [0; 256];
+    let mut level = 0.0;
+    let mut last = Instant::now();
+
+    while running.load(Ordering::Relaxed) {
+        let read = match master.read(&mut buffer) {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => continue,
+            Err(err) => {
+                eprintln!("Virtual device read failed: {err}");
+                thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+        };
+
+        let now = Instant::now();
+        let (accepted, dropped): (Vec<u8>, Vec<u8>) = match limit {
+            Some(limit) => {
+                let mut elapsed = now - last;
+                buffer[..read]
+                    .iter()
+                    .partition(|_| limit.admit(&mut level, std::mem::take(&mut elapsed)))
+            }
+            None => (buffer[..read].to_vec(), vec![]),
+        };
+        last = now;
+
+        let mut events = device.feed(&accepted);
+        events.extend(
+            dropped
+                .into_iter()
+                .map(|byte| device.record(Event::Dropped(byte))),
+        );
+        if let Some(listener) = listener.as_mut() {
+            events.iter().for_each(listener);
+        }
+    }
+}
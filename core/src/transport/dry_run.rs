@@ -0,0 +1,75 @@
+use super::Transport;
+use crate::{error::Result, sim::Simulator};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+// Prints what the device would do instead of sending anything, one decoded
+// event per line, followed by a summary once the emulator is closed
+pub struct DryRun {
+    output: Box<dyn Write + Send>,
+    decoder: Simulator,
+    bytes: usize,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::with_output(io::stdout())
+    }
+
+    pub fn with_output<W: Write + Send + 'static>(output: W) -> Self {
+        Self {
+            output: Box::new(output),
+            decoder: Simulator::new(),
+            bytes: 0,
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Default for DryRun {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for DryRun {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for event in self.decoder.feed(buf) {
+            writeln!(self.output, "{event}")?;
+        }
+        self.bytes += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+impl Transport for DryRun {
+    fn name(&self) -> Option<String> {
+        Some("dry run".to_owned())
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+
+    fn finish(&mut self, elapsed: Duration) -> Result<()> {
+        writeln!(
+            self.output,
+            "{} bytes, estimated {:.3}s",
+            self.bytes,
+            elapsed.as_secs_f64()
+        )?;
+        self.output.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use super::Transport;
+use crate::error::Result;
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
+
+pub struct File {
+    file: fs::File,
+    path: PathBuf,
+}
+
+impl File {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = fs::File::create(&path)?;
+
+        Ok(Self { file, path })
+    }
+
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Self { file, path })
+    }
+}
+
+impl io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Transport for File {
+    fn name(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+}
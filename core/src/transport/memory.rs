@@ -0,0 +1,51 @@
+use super::Transport;
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+// Clones share the same buffer, so a handle can be kept around to inspect
+// what an emulator wrote after it has taken ownership of the transport
+#[derive(Clone, Default)]
+pub struct Memory(Arc<Mutex<Vec<u8>>>);
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        match self.0.lock() {
+            Ok(buffer) => buffer.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
+    pub fn take(&self) -> Vec<u8> {
+        match self.0.lock() {
+            Ok(mut buffer) => std::mem::take(&mut *buffer),
+            Err(err) => std::mem::take(&mut *err.into_inner()),
+        }
+    }
+}
+
+impl io::Write for Memory {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Ok(mut buffer) = self.0.lock() else {
+            return Err(io::Error::other("Lock poisoned"));
+        };
+        buffer.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Memory {
+    fn name(&self) -> Option<String> {
+        Some("memory".to_owned())
+    }
+}
@@ -0,0 +1,46 @@
+mod dry_run;
+mod file;
+mod memory;
+#[cfg(unix)]
+mod pty;
+mod serial;
+mod tcp;
+
+pub use dry_run::DryRun;
+pub use file::File;
+pub use memory::Memory;
+#[cfg(unix)]
+pub use pty::Pty;
+pub use serial::Serial;
+pub use serialport::{DataBits, FlowControl, Parity, StopBits};
+pub use tcp::Tcp;
+
+use crate::error::Result;
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+// Anything the emulator can push its byte stream into
+pub trait Transport: Write + Send {
+    fn name(&self) -> Option<String> {
+        None
+    }
+
+    // Re-establishes the connection after an I/O failure
+    fn reconnect(&mut self) -> Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "transport can't reconnect").into())
+    }
+
+    // Nothing reaches a real device, pacing runs on a virtual clock instead
+    // of sleeping
+    fn is_simulated(&self) -> bool {
+        false
+    }
+
+    // Called when the emulator is closed, `elapsed` is how long the device
+    // took to process everything sent to it
+    fn finish(&mut self, _elapsed: Duration) -> Result<()> {
+        Ok(())
+    }
+}
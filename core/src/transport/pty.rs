@@ -0,0 +1,39 @@
+use super::Transport;
+use crate::error::Result;
+use serialport::{SerialPort, TTYPort};
+use std::io;
+
+// Writes go to the master side, whatever opens `path()` reads them back.
+// The slave is held open so writes don't fail while nothing is attached.
+pub struct Pty {
+    master: TTYPort,
+    slave: TTYPort,
+}
+
+impl Pty {
+    pub fn open() -> Result<Self> {
+        let (master, slave) = TTYPort::pair()?;
+
+        Ok(Self { master, slave })
+    }
+
+    pub fn path(&self) -> Option<String> {
+        self.slave.name()
+    }
+}
+
+impl io::Write for Pty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.master.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.master.flush()
+    }
+}
+
+impl Transport for Pty {
+    fn name(&self) -> Option<String> {
+        self.path()
+    }
+}
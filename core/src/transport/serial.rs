@@ -0,0 +1,79 @@
+use super::Transport;
+use crate::{discovery, error::Result};
+use serialport::{SerialPort, SerialPortBuilder};
+use std::{io, time::Duration};
+
+pub struct Serial {
+    port: Box<dyn SerialPort>,
+    builder: Option<SerialPortBuilder>,
+    // Remembered so a reconnect can find the adapter again if it comes back
+    // under a different port name
+    serial_number: Option<String>,
+}
+
+impl Serial {
+    pub fn open(port_id: &str) -> Result<Self> {
+        Self::open_with(serialport::new(port_id, 19_200).timeout(Duration::from_millis(10)))
+    }
+
+    pub fn open_with(builder: SerialPortBuilder) -> Result<Self> {
+        let port = builder.clone().open()?;
+        let serial_number = port
+            .name()
+            .and_then(|name| discovery::find_port(&name).ok().flatten())
+            .and_then(|device| device.serial_number);
+
+        Ok(Self {
+            port,
+            builder: Some(builder),
+            serial_number,
+        })
+    }
+}
+
+impl From<Box<dyn SerialPort>> for Serial {
+    fn from(port: Box<dyn SerialPort>) -> Self {
+        Self {
+            port,
+            builder: None,
+            serial_number: None,
+        }
+    }
+}
+
+impl io::Write for Serial {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.port.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+impl Transport for Serial {
+    fn name(&self) -> Option<String> {
+        self.port.name()
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let Some(mut builder) = self.builder.clone() else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "port wasn't opened from a builder",
+            )
+            .into());
+        };
+
+        if let Some(serial_number) = &self.serial_number {
+            if let Some(device) = discovery::find_serial_number(serial_number)? {
+                builder = builder.path(device.port_name);
+            }
+        }
+
+        self.port = builder.clone().open()?;
+        self.builder = Some(builder);
+
+        Ok(())
+    }
+}
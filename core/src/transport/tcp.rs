@@ -0,0 +1,87 @@
+use super::Transport;
+use crate::{
+    bridge::{Frame, FrameKind},
+    error::{Error, Result},
+};
+use std::{io, net::TcpStream, time::Duration};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Client side of the TCP bridge, every write waits for the server to hand
+// the bytes to its device so failures surface on the call that caused them
+pub struct Tcp {
+    stream: TcpStream,
+    address: String,
+    token: Option<String>,
+}
+
+impl Tcp {
+    pub fn connect(address: &str, token: Option<&str>) -> Result<Self> {
+        let token = token.map(str::to_owned);
+
+        Ok(Self {
+            stream: handshake(address, token.as_deref())?,
+            address: address.to_owned(),
+            token,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn request(&mut self, frame: Frame) -> Result<()> {
+        frame.write_to(&mut self.stream)?;
+
+        let reply = Frame::read_from(&mut self.stream)?;
+        match reply.kind {
+            FrameKind::Ok => Ok(()),
+            FrameKind::Error => Err(Error::Remote(
+                String::from_utf8_lossy(&reply.payload).into_owned(),
+            )),
+            kind => Err(Error::Protocol(format!("unexpected {kind:?} reply"))),
+        }
+    }
+}
+
+fn handshake(address: &str, token: Option<&str>) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+
+    let token = token.unwrap_or_default().as_bytes().to_vec();
+    Frame::new(FrameKind::Hello, token).write_to(&mut stream)?;
+
+    match Frame::read_from(&mut stream)?.kind {
+        FrameKind::Ok => Ok(stream),
+        FrameKind::Busy => Err(Error::Busy),
+        FrameKind::Unauthorized => Err(Error::Unauthorized),
+        kind => Err(Error::Protocol(format!("unexpected {kind:?} reply"))),
+    }
+}
+
+impl io::Write for Tcp {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.request(Frame::new(FrameKind::Data, buf.to_vec())) {
+            Ok(_) => Ok(buf.len()),
+            Err(Error::Io(err)) => Err(err),
+            Err(err) => Err(io::Error::other(err.to_string())),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Tcp {
+    fn name(&self) -> Option<String> {
+        Some(format!("tcp://{}", self.address))
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        self.stream = handshake(&self.address, self.token.as_deref())?;
+
+        Ok(())
+    }
+}
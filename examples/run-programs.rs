@@ -7,12 +7,12 @@ use hagstrom_core::{
 use std::time::Duration;
 
 fn main() -> Result<()> {
-    let port = if cfg!(windows) {
-        "COM3"
-    } else {
-        "/dev/ttyUSB0"
+    let port = match hagstrom_core::discover()?.into_iter().next() {
+        Some(device) => device.port_name,
+        None if cfg!(windows) => "COM3".to_owned(),
+        None => "/dev/ttyUSB0".to_owned(),
     };
-    let mut emulator = Emulator::new(port)?;
+    let mut emulator = Emulator::new(&port)?;
 
     login(&mut emulator)?;
     write_document_gvim(&mut emulator)?;
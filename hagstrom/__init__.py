@@ -1,2 +1 @@
-# from lib import KeyCodeMouseButton, Emulator, ScrollDirection, ScrollMagnitude 
-from lib import initialize, write_message, write_command, move, click, scroll, KeyCodeMouseButton, ScrollDirection, ScrollMagnitude
+from lib import Emulator, initialize, initialize_by_serial_number, initialize_remote, initialize_dry_run, close, discover, install_signal_handler, write_message, write_command, move, click, scroll, key_down, key_up, hold, release_all, emergency_release, enable_reconnect, disable_reconnect, enable_typing_profile, disable_typing_profile, set_layout, set_unicode_input, set_unsupported_policy, start_journal, stop_journal, KeyCode, HostLayout, UnicodeInput, UnsupportedPolicy, MouseButton, ScrollDirection, ScrollMagnitude
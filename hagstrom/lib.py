@@ -2,11 +2,32 @@ import ctypes
 from enum import Enum
 
 hagstrom = ctypes.cdll.LoadLibrary("hagstrom.dll")
-hagstrom.initialize_emulator.argtypes = [ctypes.c_char_p]
-hagstrom.write_message.argtypes = [ctypes.c_char_p, ctypes.c_uint64]
-hagstrom.mouse_move.argtypes = [ctypes.c_uint16, ctypes.c_uint16, ctypes.c_uint64]
-hagstrom.mouse_click.argtypes = [ctypes.c_uint8, ctypes.c_uint64]
-hagstrom.mouse_scroll.argtypes = [ctypes.c_uint8, ctypes.c_uint8, ctypes.c_uint64]
+hagstrom.initialize_emulator.argtypes = [ctypes.c_char_p, ctypes.POINTER(ctypes.c_uint32)]
+hagstrom.initialize_emulator_by_serial_number.argtypes = [ctypes.c_char_p, ctypes.POINTER(ctypes.c_uint32)]
+hagstrom.initialize_remote_emulator.argtypes = [ctypes.c_char_p, ctypes.c_char_p, ctypes.POINTER(ctypes.c_uint32)]
+hagstrom.initialize_dry_run_emulator.argtypes = [ctypes.POINTER(ctypes.c_uint32)]
+hagstrom.close_emulator.argtypes = [ctypes.c_uint32]
+hagstrom.discover_devices.argtypes = [ctypes.c_char_p, ctypes.c_size_t]
+hagstrom.write_message.argtypes = [ctypes.c_uint32, ctypes.c_char_p, ctypes.c_uint64]
+hagstrom.write_command.argtypes = [ctypes.c_uint32, ctypes.c_char_p, ctypes.c_uint64]
+hagstrom.mouse_move.argtypes = [ctypes.c_uint32, ctypes.c_uint16, ctypes.c_uint16, ctypes.c_uint64]
+hagstrom.mouse_click.argtypes = [ctypes.c_uint32, ctypes.c_uint8, ctypes.c_uint64]
+hagstrom.mouse_scroll.argtypes = [ctypes.c_uint32, ctypes.c_uint8, ctypes.c_uint8, ctypes.c_uint64]
+hagstrom.install_signal_handler.argtypes = []
+hagstrom.key_down.argtypes = [ctypes.c_uint32, ctypes.c_uint8]
+hagstrom.key_up.argtypes = [ctypes.c_uint32, ctypes.c_uint8]
+hagstrom.hold_keys.argtypes = [ctypes.c_uint32, ctypes.POINTER(ctypes.c_uint8), ctypes.c_size_t, ctypes.c_uint64]
+hagstrom.release_all.argtypes = [ctypes.c_uint32]
+hagstrom.emergency_release.argtypes = [ctypes.c_uint32]
+hagstrom.enable_reconnect.argtypes = [ctypes.c_uint32, ctypes.c_uint32, ctypes.c_uint64]
+hagstrom.disable_reconnect.argtypes = [ctypes.c_uint32]
+hagstrom.enable_typing_profile.argtypes = [ctypes.c_uint32, ctypes.c_double, ctypes.c_double, ctypes.c_uint64, ctypes.c_uint64, ctypes.c_double, ctypes.c_uint64]
+hagstrom.disable_typing_profile.argtypes = [ctypes.c_uint32]
+hagstrom.set_layout.argtypes = [ctypes.c_uint32, ctypes.c_uint8]
+hagstrom.set_unicode_input.argtypes = [ctypes.c_uint32, ctypes.c_uint8]
+hagstrom.set_unsupported_policy.argtypes = [ctypes.c_uint32, ctypes.c_uint8, ctypes.c_uint32]
+hagstrom.start_journal.argtypes = [ctypes.c_uint32, ctypes.c_char_p]
+hagstrom.stop_journal.argtypes = [ctypes.c_uint32]
 
 class ResponseCode(Enum):
     Ok = 0
@@ -14,6 +35,14 @@ class ResponseCode(Enum):
     DataFormatting = 2
     DeviceNotFound = 3
     LockPoisoned = 4
+    Interrupted = 5
+    SignalHandler = 6
+    BufferTooSmall = 7
+    Busy = 8
+    Unauthorized = 9
+    UnsupportedCharacter = 10
+    InvalidSequence = 11
+    Io = 12
 
 
 class KeyCode(Enum): 
@@ -94,6 +123,65 @@ class KeyCode(Enum):
     F11 = 71
     F12 = 72
 
+    # No F13-F24, the device has no key numbers for them
+    RShift = 73
+    RControl = 74
+    RAlt = 75
+    RSuper = 76
+    Menu = 77
+
+    Insert = 78
+    Delete = 79
+    Home = 80
+    End = 81
+    PageUp = 82
+    PageDown = 83
+
+    PrintScreen = 84
+    ScrollLock = 85
+    Pause = 86
+    NumLock = 87
+
+    KeypadZero = 88
+    KeypadOne = 89
+    KeypadTwo = 90
+    KeypadThree = 91
+    KeypadFour = 92
+    KeypadFive = 93
+    KeypadSix = 94
+    KeypadSeven = 95
+    KeypadEight = 96
+    KeypadNine = 97
+    KeypadDivide = 98
+    KeypadMultiply = 99
+    KeypadMinus = 100
+    KeypadPlus = 101
+    KeypadEnter = 102
+    KeypadPeriod = 103
+
+    NonUsBackSlash = 104
+    NonUsHash = 105
+
+class HostLayout(Enum):
+    UsQwerty = 0
+    Uk = 1
+    German = 2
+    French = 3
+    Swiss = 4
+    Nordic = 5
+    Dvorak = 6
+
+class UnicodeInput(Enum):
+    Disabled = 0
+    WindowsAltCode = 1
+    LinuxHex = 2
+    MacHex = 3
+
+class UnsupportedPolicy(Enum):
+    Fail = 0
+    Skip = 1
+    Replace = 2
+
 class MouseButton(Enum):
     Left = 0
     Middle = 1
@@ -114,47 +202,204 @@ class ScrollMagnitude(Enum):
     Zero = 0
 
 class Emulator:
-    def __init__(self, serial_port):
-        self.id = serial_port 
-        handle_response(hagstrom.initialize_emulator(serial_port.encode("utf-8")))
+    def __init__(self, serial_port: str = None, serial_number: str = None, address: str = None, token: str = None, dry_run: bool = False):
+        handle = ctypes.c_uint32()
+        if dry_run:
+            handle_response(hagstrom.initialize_dry_run_emulator(ctypes.byref(handle)))
+        elif address is not None:
+            token = token.encode("utf-8") if token is not None else None
+            handle_response(hagstrom.initialize_remote_emulator(address.encode("utf-8"), token, ctypes.byref(handle)))
+        elif serial_number is not None:
+            handle_response(hagstrom.initialize_emulator_by_serial_number(serial_number.encode("utf-8"), ctypes.byref(handle)))
+        else:
+            handle_response(hagstrom.initialize_emulator(serial_port.encode("utf-8"), ctypes.byref(handle)))
+            
+        self.id = serial_port or serial_number or address or "dry run"
+        self.handle = handle.value
+        
+    def close(self):
+        handle_response(hagstrom.close_emulator(self.handle))
     
-    def write_message(message, timeout):
-        handle_response(hagstrom.write_message(message.encode("utf-8"), timeout))
+    def write_message(self, message: str, timeout: int):
+        handle_response(hagstrom.write_message(self.handle, message.encode("utf-8"), timeout))
     
-    def write_command(keycodes, timeout):
-        bytes = list(map(lambda keycode: keycode.value, keycodes))
-        keycodes = (ctypes.c_uint8 * len(bytes))(*bytes) 
-        handle_response(hagstrom.write_command(keycodes, timeout))
+    # `command` is a key sequence such as "ctrl+alt+t", "<C-S-Esc>", "{Enter 2}" or "^c",
+    # a list of keys is pressed together and released in reverse
+    def write_command(self, command: str | list[KeyCode], timeout: int):
+        if not isinstance(command, str):
+            command = "".join([f"{{{keycode.name} down}}" for keycode in command] +
+                              [f"{{{keycode.name} up}}" for keycode in reversed(command)])
+        handle_response(hagstrom.write_command(self.handle, command.encode("utf-8"), timeout))
         
-    def move(x: int, y: int, timeout: int):
-        handle_response(hagstrom.mouse_move(x, y, timeout))
+    def move(self, x: int, y: int, timeout: int):
+        handle_response(hagstrom.mouse_move(self.handle, x, y, timeout))
     
-    def click(button: MouseButton, timeout: int):
-        handle_response(hagstrom.mouse_click(button.value, timeout))
+    def click(self, button: MouseButton, timeout: int):
+        handle_response(hagstrom.mouse_click(self.handle, button.value, timeout))
     
-    def scroll(direction: ScrollDirection, magnitude: ScrollMagnitude, timeout: int):
-        handle_response(hagstrom.mouse_scroll(direction.value, magnitude.value, timeout))
+    def scroll(self, direction: ScrollDirection, magnitude: ScrollMagnitude, timeout: int):
+        handle_response(hagstrom.mouse_scroll(self.handle, direction.value, magnitude.value, timeout))
+
+    def key_down(self, keycode: KeyCode):
+        handle_response(hagstrom.key_down(self.handle, keycode.value))
+
+    def key_up(self, keycode: KeyCode):
+        handle_response(hagstrom.key_up(self.handle, keycode.value))
+
+    def hold(self, keycodes: list[KeyCode], duration: int):
+        keys = (ctypes.c_uint8 * len(keycodes))(*[keycode.value for keycode in keycodes])
+        handle_response(hagstrom.hold_keys(self.handle, keys, len(keycodes), duration))
+
+    def release_all(self):
+        handle_response(hagstrom.release_all(self.handle))
+
+    def emergency_release(self):
+        handle_response(hagstrom.emergency_release(self.handle))
+        
+    def enable_reconnect(self, max_attempts: int, backoff: int):
+        handle_response(hagstrom.enable_reconnect(self.handle, max_attempts, backoff))
+
+    def disable_reconnect(self):
+        handle_response(hagstrom.disable_reconnect(self.handle))
+
+    def enable_typing_profile(self, wpm: float, jitter: float = 0.2, punctuation_pause: int = 250,
+                              newline_pause: int = 600, typo_rate: float = 0.0, seed: int = 0):
+        handle_response(hagstrom.enable_typing_profile(self.handle, wpm, jitter, punctuation_pause,
+                                                       newline_pause, typo_rate, seed))
+
+    def disable_typing_profile(self):
+        handle_response(hagstrom.disable_typing_profile(self.handle))
+
+    def set_layout(self, layout: HostLayout):
+        handle_response(hagstrom.set_layout(self.handle, layout.value))
+
+    def set_unicode_input(self, unicode: UnicodeInput):
+        handle_response(hagstrom.set_unicode_input(self.handle, unicode.value))
+
+    def set_unsupported_policy(self, policy: UnsupportedPolicy, replacement: str = "?"):
+        handle_response(hagstrom.set_unsupported_policy(self.handle, policy.value, ord(replacement)))
+
+    def start_journal(self, path: str):
+        handle_response(hagstrom.start_journal(self.handle, path.encode("utf-8")))
+
+    def stop_journal(self):
+        handle_response(hagstrom.stop_journal(self.handle))
+
 
+# Module level functions drive a single session emulator
+session = None
+
+def session_emulator() -> Emulator:
+    if session is None:
+        handle_response(ResponseCode.Uninitialized.value)
+        
+    return session
+
+def install_signal_handler():
+    handle_response(hagstrom.install_signal_handler())
 
 def initialize(serial_port: str):
-    handle_response(hagstrom.initialize_emulator(serial_port.encode("utf-8")))
+    global session
+    session = Emulator(serial_port = serial_port)
+
+def initialize_by_serial_number(serial_number: str):
+    global session
+    session = Emulator(serial_number = serial_number)
+    
+def initialize_remote(address: str, token: str = None):
+    global session
+    session = Emulator(address = address, token = token)
+
+def initialize_dry_run():
+    global session
+    session = Emulator(dry_run = True)
+
+def close():
+    global session
+    session_emulator().close()
+    session = None
+
+def discover() -> list[dict]:
+    # Grows the buffer until the listing fits
+    size = 4096
+    while True:
+        buffer = ctypes.create_string_buffer(size)
+        response = hagstrom.discover_devices(buffer, len(buffer))
+        if response != ResponseCode.BufferTooSmall.value:
+            break
+        size *= 2
+    handle_response(response)
+    
+    devices = []
+    for line in buffer.value.decode("utf-8").splitlines():
+        port_name, serial_number, manufacturer, product, confidence = line.split("\t")
+        devices.append({
+            "port_name": port_name,
+            "serial_number": serial_number,
+            "manufacturer": manufacturer,
+            "product": product,
+            "confidence": confidence,
+        })
+        
+    return devices
     
 def write_message(message: str, timeout: int):
-    handle_response(hagstrom.write_message(message.encode("utf-8"), timeout))
+    session_emulator().write_message(message, timeout)
     
-def write_command(keycodes: list[KeyCode], timeout: int):
-    bytes = list(map(lambda keycode: keycode.value, keycodes))
-    keycodes = (ctypes.c_uint8 * len(bytes))(*bytes) 
-    handle_response(hagstrom.write_command(keycodes, timeout))
+def write_command(command: str | list[KeyCode], timeout: int):
+    session_emulator().write_command(command, timeout)
 
 def move(x: int, y: int, timeout: int):
-    handle_response(hagstrom.mouse_move(x, y, timeout))
+    session_emulator().move(x, y, timeout)
 
 def click(button: MouseButton, timeout: int):
-    handle_response(hagstrom.mouse_click(button.value, timeout))
+    session_emulator().click(button, timeout)
 
 def scroll(direction: ScrollDirection, magnitude: ScrollMagnitude, timeout: int):
-    handle_response(hagstrom.mouse_scroll(direction.value, magnitude.value, timeout))
+    session_emulator().scroll(direction, magnitude, timeout)
+    
+def key_down(keycode: KeyCode):
+    session_emulator().key_down(keycode)
+
+def key_up(keycode: KeyCode):
+    session_emulator().key_up(keycode)
+
+def hold(keycodes: list[KeyCode], duration: int):
+    session_emulator().hold(keycodes, duration)
+
+def release_all():
+    session_emulator().release_all()
+
+def emergency_release():
+    session_emulator().emergency_release()
+    
+def enable_reconnect(max_attempts: int, backoff: int):
+    session_emulator().enable_reconnect(max_attempts, backoff)
+
+def disable_reconnect():
+    session_emulator().disable_reconnect()
+
+def enable_typing_profile(wpm: float, jitter: float = 0.2, punctuation_pause: int = 250,
+                          newline_pause: int = 600, typo_rate: float = 0.0, seed: int = 0):
+    session_emulator().enable_typing_profile(wpm, jitter, punctuation_pause, newline_pause, typo_rate, seed)
+
+def disable_typing_profile():
+    session_emulator().disable_typing_profile()
+
+def set_layout(layout: HostLayout):
+    session_emulator().set_layout(layout)
+
+def set_unicode_input(unicode: UnicodeInput):
+    session_emulator().set_unicode_input(unicode)
+
+def set_unsupported_policy(policy: UnsupportedPolicy, replacement: str = "?"):
+    session_emulator().set_unsupported_policy(policy, replacement)
+
+def start_journal(path: str):
+    session_emulator().start_journal(path)
+
+def stop_journal():
+    session_emulator().stop_journal()
     
 def handle_response(response: ResponseCode):
     if response != 0:
@@ -167,5 +412,21 @@ def handle_response(response: ResponseCode):
                 print("Device not found")
             case ResponseCode.LockPoisoned:
                 print("Lock poisoned")
+            case ResponseCode.Interrupted:
+                print("Interrupted by signal")
+            case ResponseCode.SignalHandler:
+                print("Failed to install signal handler")
+            case ResponseCode.BufferTooSmall:
+                print("Buffer too small")
+            case ResponseCode.Busy:
+                print("Device is in use by another client")
+            case ResponseCode.Unauthorized:
+                print("Authentication failed")
+            case ResponseCode.UnsupportedCharacter:
+                print("Message contains characters that can't be typed")
+            case ResponseCode.InvalidSequence:
+                print("Invalid key sequence")
+            case ResponseCode.Io:
+                print("Device I/O error")
                 
         quit()
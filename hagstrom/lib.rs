@@ -1,19 +1,18 @@
 use hagstrom_core::{
-    action::{KeyCode, MouseAction, MouseButton, ScrollDirection, ScrollMagnitude},
-    message, Emulator,
+    action::{
+        key::UnsupportedPolicy, HostLayout, Jitter, KeyCode, MouseAction, MouseButton,
+        ScrollDirection, ScrollMagnitude, TypingProfile, UnicodeInput,
+    },
+    discover,
+    error::Error,
+    journal::Kind,
+    signal, Emulator, ReconnectPolicy,
 };
-use lazy_static::lazy_static;
 use num_enum::TryFromPrimitiveError;
-use std::{
-    ffi::CStr,
-    sync::{Arc, Mutex},
-    time::Duration,
-};
+use registry::{with_emulator, Handle};
+use std::{ffi::CStr, sync::Arc, time::Duration};
 
-lazy_static! {
-    static ref SESSION_EMULATOR: Arc<Mutex<Option<Emulator>>> = Arc::new(Mutex::new(None));
-    static ref EMULATORS: Arc<Mutex<Vec<Emulator>>> = Arc::new(Mutex::new(vec![]));
-}
+mod registry;
 
 #[repr(C)]
 enum ResponseCode {
@@ -22,10 +21,43 @@ enum ResponseCode {
     DataFormatting = 2,
     DeviceNotFound = 3,
     LockPoisoned = 4,
+    Interrupted = 5,
+    SignalHandler = 6,
+    BufferTooSmall = 7,
+    Busy = 8,
+    Unauthorized = 9,
+    UnsupportedCharacter = 10,
+    InvalidSequence = 11,
+    Io = 12,
+}
+
+impl From<Error> for ResponseCode {
+    fn from(err: Error) -> Self {
+        match err {
+            err if err.is_not_found() => Self::DeviceNotFound,
+            Error::Io(_) | Error::SerialPort(_) => Self::Io,
+            Error::Interrupted => Self::Interrupted,
+            Error::Signal(_) => Self::SignalHandler,
+            Error::Poison(_) => Self::LockPoisoned,
+            Error::Busy => Self::Busy,
+            Error::Unauthorized => Self::Unauthorized,
+            Error::UnsupportedChars(_) => Self::UnsupportedCharacter,
+            Error::Sequence { .. } => Self::InvalidSequence,
+            _ => Self::DataFormatting,
+        }
+    }
 }
 
 #[no_mangle]
-extern "C" fn initialize_emulator(serial_port: *const i8) -> ResponseCode {
+extern "C" fn install_signal_handler() -> ResponseCode {
+    match signal::install() {
+        Ok(_) => ResponseCode::Ok,
+        Err(err) => err.into(),
+    }
+}
+
+#[no_mangle]
+extern "C" fn initialize_emulator(serial_port: *const i8, handle: *mut Handle) -> ResponseCode {
     let serial_port = unsafe {
         match convert_c_str(serial_port) {
             Ok(data) => data,
@@ -33,129 +65,384 @@ extern "C" fn initialize_emulator(serial_port: *const i8) -> ResponseCode {
         }
     };
 
-    match Emulator::new(serial_port) {
-        Ok(emulator) => match SESSION_EMULATOR.lock().as_mut() {
-            Ok(session_emulator) => {
-                **session_emulator = Some(emulator);
+    register(handle, || Emulator::new(serial_port))
+}
+
+#[no_mangle]
+extern "C" fn initialize_emulator_by_serial_number(
+    serial_number: *const i8,
+    handle: *mut Handle,
+) -> ResponseCode {
+    let serial_number = unsafe {
+        match convert_c_str(serial_number) {
+            Ok(data) => data,
+            Err(response_code) => return response_code,
+        }
+    };
+
+    register(handle, || Emulator::open_serial_number(serial_number))
+}
 
-                ResponseCode::Ok
+// Decoded events and a summary go to stdout instead of a device
+#[no_mangle]
+extern "C" fn initialize_dry_run_emulator(handle: *mut Handle) -> ResponseCode {
+    register(handle, || Ok(Emulator::dry_run()))
+}
+
+// `token` may be null when the bridge doesn't require one
+#[no_mangle]
+extern "C" fn initialize_remote_emulator(
+    address: *const i8,
+    token: *const i8,
+    handle: *mut Handle,
+) -> ResponseCode {
+    let address = unsafe {
+        match convert_c_str(address) {
+            Ok(data) => data,
+            Err(response_code) => return response_code,
+        }
+    };
+    let token = match token.is_null() {
+        true => None,
+        false => unsafe {
+            match convert_c_str(token) {
+                Ok(data) => Some(data),
+                Err(response_code) => return response_code,
             }
-            Err(_) => ResponseCode::LockPoisoned,
         },
-        Err(_) => ResponseCode::DeviceNotFound,
-    }
+    };
+
+    register(handle, || Emulator::connect(address, token))
 }
 
-// #[no_mangle]
-// extern "C" fn initialize_emulator(serial_port: *const i8) -> ResponseCode {
-//     let serial_port = unsafe {
-//         match convert_c_str(serial_port) {
-//             Ok(data) => data,
-//             Err(response_code) => return response_code,
-//         }
-//     };
-
-//     match Emulator::new(serial_port) {
-//         Ok(emulator) => match EMULATORS.lock().as_mut() {
-//             Ok(emulators) => {
-//                 emulators.push(emulator);
-
-//                 ResponseCode::Ok
-//             }
-//             Err(_) => ResponseCode::LockPoisoned,
-//         },
-//         Err(_) => ResponseCode::DeviceNotFound,
-//     }
-// }
-
-fn send_packet<F>(packet_callback: F, sleep_duration: u64) -> ResponseCode
+// Opens the emulator and writes its handle, a null `handle` fails before
+// anything is opened
+fn register<F>(handle: *mut Handle, open: F) -> ResponseCode
 where
-    F: FnOnce() -> Result<Vec<u8>, ResponseCode>,
+    F: FnOnce() -> hagstrom_core::error::Result<Emulator>,
 {
-    let Ok(mut emulator_lock) = SESSION_EMULATOR.lock() else {
-        return ResponseCode::LockPoisoned;
+    if handle.is_null() {
+        return ResponseCode::DataFormatting;
+    }
+
+    let emulator = match open() {
+        Ok(emulator) => emulator,
+        Err(err) => return err.into(),
     };
 
-    let Some(emulator) = emulator_lock.as_mut() else {
-        return ResponseCode::Uninitialized;
+    match registry::insert(emulator) {
+        Ok(new_handle) => {
+            unsafe { *handle = new_handle };
+
+            ResponseCode::Ok
+        }
+        Err(response_code) => response_code,
+    }
+}
+
+// Releases everything the device holds before dropping it. If another call is
+// still using the emulator it is released once that call returns.
+#[no_mangle]
+extern "C" fn close_emulator(handle: Handle) -> ResponseCode {
+    let emulator = match registry::remove(handle) {
+        Ok(emulator) => emulator,
+        Err(response_code) => return response_code,
     };
 
+    match Arc::try_unwrap(emulator) {
+        Ok(emulator) => match emulator.into_inner() {
+            Ok(emulator) => match emulator.close() {
+                Ok(_) => ResponseCode::Ok,
+                Err(err) => err.into(),
+            },
+            Err(_) => ResponseCode::LockPoisoned,
+        },
+        Err(_) => ResponseCode::Ok,
+    }
+}
+
+// Writes one `port\tserial number\tmanufacturer\tproduct\tconfidence` line
+// per device into `buffer` as a nul terminated string, confidence being
+// `confirmed` or `possible`. A null `buffer` is a formatting error.
+#[no_mangle]
+extern "C" fn discover_devices(buffer: *mut i8, capacity: usize) -> ResponseCode {
+    if buffer.is_null() {
+        return ResponseCode::DataFormatting;
+    }
+
+    let Ok(devices) = discover() else {
+        return ResponseCode::DeviceNotFound;
+    };
+
+    let listing: String = devices
+        .into_iter()
+        .map(|device| {
+            let confidence = device.confidence().map(|c| c.name()).unwrap_or_default();
+            format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                device.port_name,
+                device.serial_number.unwrap_or_default(),
+                device.manufacturer.unwrap_or_default(),
+                device.product.unwrap_or_default(),
+                confidence,
+            )
+        })
+        .collect();
+
+    if listing.len() >= capacity {
+        return ResponseCode::BufferTooSmall;
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(listing.as_ptr(), buffer as *mut u8, listing.len());
+        *buffer.add(listing.len()) = 0;
+    }
+
+    ResponseCode::Ok
+}
+
+fn send_packet<F>(
+    handle: Handle,
+    kind: Kind,
+    packet_callback: F,
+    sleep_duration: u64,
+) -> ResponseCode
+where
+    F: FnOnce() -> Result<Vec<u8>, ResponseCode>,
+{
     let packet = match packet_callback() {
         Ok(packet) => packet,
         Err(response_code) => return response_code,
     };
 
-    match emulator.write(packet, Duration::from_millis(sleep_duration)) {
-        Ok(_) => ResponseCode::Ok,
-        Err(_) => ResponseCode::DataFormatting,
-    }
+    with_emulator(handle, |emulator| {
+        emulator.write_as(kind, packet, Duration::from_millis(sleep_duration))
+    })
 }
 
 #[no_mangle]
-extern "C" fn write_message(message: *const i8, sleep_duration: u64) -> ResponseCode {
-    match SESSION_EMULATOR.lock() {
-        Ok(mut emulator) => match emulator.as_mut() {
-            Some(emulator) => {
-                let data = unsafe {
-                    match convert_c_str(message) {
-                        Ok(data) => data,
-                        Err(response_code) => return response_code,
-                    }
-                };
-                let packet = message!(data);
-                let duration = Duration::from_millis(sleep_duration);
-
-                match emulator.write(packet, duration) {
-                    Ok(_) => ResponseCode::Ok,
-                    Err(_) => ResponseCode::DataFormatting,
-                }
-            }
-            None => ResponseCode::Uninitialized,
-        },
-        Err(_) => ResponseCode::LockPoisoned,
+extern "C" fn key_down(handle: Handle, key: u8) -> ResponseCode {
+    let Ok(key) = KeyCode::try_from(key) else {
+        return ResponseCode::DataFormatting;
+    };
+
+    with_emulator(handle, |emulator| emulator.key_down(key))
+}
+
+#[no_mangle]
+extern "C" fn key_up(handle: Handle, key: u8) -> ResponseCode {
+    let Ok(key) = KeyCode::try_from(key) else {
+        return ResponseCode::DataFormatting;
+    };
+
+    with_emulator(handle, |emulator| emulator.key_up(key))
+}
+
+// `keys` points to `count` `KeyCode` values, a null `keys` is a formatting error
+#[no_mangle]
+extern "C" fn hold_keys(
+    handle: Handle,
+    keys: *const u8,
+    count: usize,
+    duration: u64,
+) -> ResponseCode {
+    if keys.is_null() {
+        return ResponseCode::DataFormatting;
     }
+
+    let keys = unsafe { std::slice::from_raw_parts(keys, count) };
+    let Ok(keys) = keys
+        .iter()
+        .map(|key| KeyCode::try_from(*key))
+        .collect::<Result<Vec<KeyCode>, TryFromPrimitiveError<KeyCode>>>()
+    else {
+        return ResponseCode::DataFormatting;
+    };
+
+    with_emulator(handle, |emulator| {
+        emulator.hold(&keys, Duration::from_millis(duration))
+    })
 }
 
 #[no_mangle]
-extern "C" fn write_command(message: *const i8, sleep_duration: u64) -> ResponseCode {
-    match SESSION_EMULATOR.lock() {
-        Ok(mut emulator) => match emulator.as_mut() {
-            Some(emulator) => {
-                let duration = Duration::from_millis(sleep_duration);
-                let data = unsafe {
-                    match convert_c_str(message) {
-                        Ok(data) => data,
-                        Err(response_code) => return response_code,
-                    }
-                };
-
-                let Ok(packet) = data
-                    .chars()
-                    .map(|char| KeyCode::try_from(char as u8))
-                    .collect::<Result<Vec<KeyCode>, TryFromPrimitiveError<KeyCode>>>()
-                    .map(hagstrom_core::action::key::create_command) else {
-                     return ResponseCode::DataFormatting;
-                };
-
-                match emulator.write(packet, duration) {
-                    Ok(_) => ResponseCode::Ok,
-                    Err(_) => ResponseCode::DataFormatting,
-                }
-            }
-            None => ResponseCode::Uninitialized,
-        },
-        Err(_) => ResponseCode::LockPoisoned,
+extern "C" fn release_all(handle: Handle) -> ResponseCode {
+    with_emulator(handle, Emulator::release_all)
+}
+
+#[no_mangle]
+extern "C" fn emergency_release(handle: Handle) -> ResponseCode {
+    with_emulator(handle, Emulator::emergency_release)
+}
+
+// Keyboard layout the target is set to, as numbered in `HostLayout`
+#[no_mangle]
+extern "C" fn set_layout(handle: Handle, layout: u8) -> ResponseCode {
+    let Ok(layout) = HostLayout::try_from(layout) else {
+        return ResponseCode::DataFormatting;
+    };
+
+    with_emulator(handle, |emulator| {
+        emulator.set_layout(layout);
+
+        Ok(())
+    })
+}
+
+// Fallback for characters the layout can't type, as numbered in `UnicodeInput`
+#[no_mangle]
+extern "C" fn set_unicode_input(handle: Handle, unicode: u8) -> ResponseCode {
+    let Ok(unicode) = UnicodeInput::try_from(unicode) else {
+        return ResponseCode::DataFormatting;
+    };
+
+    with_emulator(handle, |emulator| {
+        emulator.set_unicode_input(unicode);
+
+        Ok(())
+    })
+}
+
+// 0 fails the whole message, 1 skips the character, 2 types `replacement`
+// in its place
+#[no_mangle]
+extern "C" fn set_unsupported_policy(handle: Handle, policy: u8, replacement: u32) -> ResponseCode {
+    let policy = match (policy, char::from_u32(replacement)) {
+        (0, _) => UnsupportedPolicy::Fail,
+        (1, _) => UnsupportedPolicy::Skip,
+        (2, Some(replacement)) => UnsupportedPolicy::Replace(replacement),
+        _ => return ResponseCode::DataFormatting,
+    };
+
+    with_emulator(handle, |emulator| {
+        emulator.set_unsupported_policy(policy);
+
+        Ok(())
+    })
+}
+
+#[no_mangle]
+extern "C" fn start_journal(handle: Handle, path: *const i8) -> ResponseCode {
+    let path = unsafe {
+        match convert_c_str(path) {
+            Ok(data) => data,
+            Err(response_code) => return response_code,
+        }
+    };
+
+    with_emulator(handle, |emulator| emulator.start_journal(path))
+}
+
+#[no_mangle]
+extern "C" fn stop_journal(handle: Handle) -> ResponseCode {
+    with_emulator(handle, |emulator| {
+        emulator.stop_journal();
+
+        Ok(())
+    })
+}
+
+#[no_mangle]
+extern "C" fn enable_reconnect(handle: Handle, max_attempts: u32, backoff: u64) -> ResponseCode {
+    if max_attempts == 0 {
+        return ResponseCode::DataFormatting;
     }
+
+    with_emulator(handle, |emulator| {
+        emulator.set_reconnect_policy(Some(ReconnectPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(backoff),
+            ..Default::default()
+        }));
+
+        Ok(())
+    })
 }
 
-#[inline]
 #[no_mangle]
-extern "C" fn mouse_move(x: u16, y: u16, sleep_duration: u64) -> ResponseCode {
-    send_packet(|| Ok(MouseAction::Move(x, y).as_packet()), sleep_duration)
+extern "C" fn disable_reconnect(handle: Handle) -> ResponseCode {
+    with_emulator(handle, |emulator| {
+        emulator.set_reconnect_policy(None);
+
+        Ok(())
+    })
 }
 
+// `jitter` is the standard deviation of each gap as a fraction of the average
 #[no_mangle]
-extern "C" fn mouse_click(button: u8, sleep_duration: u64) -> ResponseCode {
+extern "C" fn enable_typing_profile(
+    handle: Handle,
+    wpm: f64,
+    jitter: f64,
+    punctuation_pause: u64,
+    newline_pause: u64,
+    typo_rate: f64,
+    seed: u64,
+) -> ResponseCode {
+    let profile = TypingProfile::new(wpm)
+        .jitter(Jitter::Normal(jitter))
+        .punctuation_pause(Duration::from_millis(punctuation_pause))
+        .newline_pause(Duration::from_millis(newline_pause))
+        .typos(typo_rate)
+        .seed(seed);
+
+    with_emulator(handle, |emulator| {
+        emulator.set_typing_profile(Some(profile))
+    })
+}
+
+#[no_mangle]
+extern "C" fn disable_typing_profile(handle: Handle) -> ResponseCode {
+    with_emulator(handle, |emulator| emulator.set_typing_profile(None))
+}
+
+#[no_mangle]
+extern "C" fn write_message(
+    handle: Handle,
+    message: *const i8,
+    sleep_duration: u64,
+) -> ResponseCode {
+    let data = unsafe {
+        match convert_c_str(message) {
+            Ok(data) => data,
+            Err(response_code) => return response_code,
+        }
+    };
+
+    with_emulator(handle, |emulator| {
+        emulator.write_message(data, Duration::from_millis(sleep_duration))
+    })
+}
+
+#[no_mangle]
+extern "C" fn write_command(
+    handle: Handle,
+    message: *const i8,
+    sleep_duration: u64,
+) -> ResponseCode {
+    let data = unsafe {
+        match convert_c_str(message) {
+            Ok(data) => data,
+            Err(response_code) => return response_code,
+        }
+    };
+
+    with_emulator(handle, |emulator| {
+        emulator.write_sequence(data, Duration::from_millis(sleep_duration))
+    })
+}
+
+#[no_mangle]
+extern "C" fn mouse_move(handle: Handle, x: u16, y: u16, sleep_duration: u64) -> ResponseCode {
+    send_packet(
+        handle,
+        Kind::Mouse,
+        || Ok(MouseAction::Move(x, y).as_packet()),
+        sleep_duration,
+    )
+}
+
+#[no_mangle]
+extern "C" fn mouse_click(handle: Handle, button: u8, sleep_duration: u64) -> ResponseCode {
     let callback = || {
         let Ok(button) = MouseButton::try_from(button) else {
             return Err(ResponseCode::DataFormatting);
@@ -164,11 +451,16 @@ extern "C" fn mouse_click(button: u8, sleep_duration: u64) -> ResponseCode {
         Ok(MouseAction::from(button).as_packet())
     };
 
-    send_packet(callback, sleep_duration)
+    send_packet(handle, Kind::Mouse, callback, sleep_duration)
 }
 
 #[no_mangle]
-extern "C" fn mouse_scroll(direction: u8, magnitude: u8, sleep_duration: u64) -> ResponseCode {
+extern "C" fn mouse_scroll(
+    handle: Handle,
+    direction: u8,
+    magnitude: u8,
+    sleep_duration: u64,
+) -> ResponseCode {
     let callback = || -> Result<Vec<u8>, ResponseCode> {
         let Ok(direction) = ScrollDirection::try_from(direction) else {
             return Err(ResponseCode::DataFormatting);
@@ -181,21 +473,17 @@ extern "C" fn mouse_scroll(direction: u8, magnitude: u8, sleep_duration: u64) ->
         Ok(MouseAction::Scroll(direction, magnitude).as_packet())
     };
 
-    send_packet(callback, sleep_duration)
+    send_packet(handle, Kind::Mouse, callback, sleep_duration)
 }
 
 unsafe fn convert_c_str<'a>(buffer: *const i8) -> Result<&'a str, ResponseCode> {
+    if buffer.is_null() {
+        return Err(ResponseCode::DataFormatting);
+    }
+
     let c_str = unsafe { CStr::from_ptr(buffer) };
     match std::str::from_utf8(c_str.to_bytes()) {
         Ok(data) => Ok(data),
         Err(_) => Err(ResponseCode::DataFormatting),
     }
 }
-
-// fn emulator_mut<'a>() -> Result<&'a mut Emulator, ResponseCode> {
-//     let Ok(mut emulator_lock) = SESSION_EMULATOR.lock() else {
-//         return Err(ResponseCode::LockPoisoned);
-//     };
-
-//     emulator_lock.as_mut().ok_or(ResponseCode::Uninitialized)
-// }
@@ -0,0 +1,70 @@
+use crate::ResponseCode;
+use hagstrom_core::{error::Result, Emulator};
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+pub type Handle = u32;
+
+// Handles start at 1 so 0 is never valid, each emulator sits behind its own
+// lock so a slow device only blocks callers of that device
+static NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+
+lazy_static! {
+    static ref EMULATORS: RwLock<HashMap<Handle, Arc<Mutex<Emulator>>>> =
+        RwLock::new(HashMap::new());
+}
+
+pub fn insert(emulator: Emulator) -> std::result::Result<Handle, ResponseCode> {
+    let Ok(mut emulators) = EMULATORS.write() else {
+        return Err(ResponseCode::LockPoisoned);
+    };
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    emulators.insert(handle, Arc::new(Mutex::new(emulator)));
+
+    Ok(handle)
+}
+
+pub fn get(handle: Handle) -> std::result::Result<Arc<Mutex<Emulator>>, ResponseCode> {
+    let Ok(emulators) = EMULATORS.read() else {
+        return Err(ResponseCode::LockPoisoned);
+    };
+
+    emulators
+        .get(&handle)
+        .cloned()
+        .ok_or(ResponseCode::Uninitialized)
+}
+
+pub fn remove(handle: Handle) -> std::result::Result<Arc<Mutex<Emulator>>, ResponseCode> {
+    let Ok(mut emulators) = EMULATORS.write() else {
+        return Err(ResponseCode::LockPoisoned);
+    };
+
+    emulators.remove(&handle).ok_or(ResponseCode::Uninitialized)
+}
+
+pub fn with_emulator<F>(handle: Handle, callback: F) -> ResponseCode
+where
+    F: FnOnce(&mut Emulator) -> Result<()>,
+{
+    let emulator = match get(handle) {
+        Ok(emulator) => emulator,
+        Err(response_code) => return response_code,
+    };
+
+    let Ok(mut emulator) = emulator.lock() else {
+        return ResponseCode::LockPoisoned;
+    };
+
+    match callback(&mut emulator) {
+        Ok(_) => ResponseCode::Ok,
+        Err(err) => err.into(),
+    }
+}